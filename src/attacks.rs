@@ -0,0 +1,334 @@
+//! Attack tables: the squares each piece attacks from each square.
+//! 
+//! Knight, king and pawn attacks never depend on the other pieces,
+//! so they are worked out in full at compile time. Sliding attacks
+//! are looked up with magic bitboards: the blockers relevant to a slider
+//! are multiplied by a magic number whose top bits index a table of
+//! precomputed attacks. Those tables are filled in the first time they
+//! are needed, or up front by calling `init`.
+
+use std::sync::OnceLock;
+
+use crate::bitboard::Bitboard;
+use crate::pieces::{Colour, DIAGONALS, KING_STEPS, KNIGHT_JUMPS, ORTHOGONALS};
+
+const KNIGHT: [Bitboard; 64] = leaper_table(&KNIGHT_JUMPS);
+const KING: [Bitboard; 64] = leaper_table(&KING_STEPS);
+// indexed by colour, in the declaration order of Colour
+const PAWN: [[Bitboard; 64]; 2] = [leaper_table(&[(-1, -1), (-1, 1)]), leaper_table(&[(1, -1), (1, 1)])];
+
+/// Returns the squares a knight on the given square attacks.
+#[inline]
+pub fn knight_attacks(square: usize) -> Bitboard {
+    KNIGHT[square]
+}
+
+/// Returns the squares a king on the given square attacks.
+#[inline]
+pub fn king_attacks(square: usize) -> Bitboard {
+    KING[square]
+}
+
+/// Returns the squares a pawn of the given colour on the given square attacks.
+#[inline]
+pub fn pawn_attacks(square: usize, colour: Colour) -> Bitboard {
+    PAWN[colour as usize][square]
+}
+
+/// Returns the squares a bishop on the given square attacks,
+/// stopping at (and including) the first piece in `occupied` along each diagonal.
+#[inline]
+pub fn bishop_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    tables.attacks[tables.bishop[square].index(occupied)]
+}
+
+/// Returns the squares a rook on the given square attacks,
+/// stopping at (and including) the first piece in `occupied` along each line.
+#[inline]
+pub fn rook_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    tables.attacks[tables.rook[square].index(occupied)]
+}
+
+/// Returns the squares a queen on the given square attacks.
+#[inline]
+pub fn queen_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    bishop_attacks(square, occupied) | rook_attacks(square, occupied)
+}
+
+/// Returns the squares strictly between two squares on the same line,
+/// or nothing if they do not share a rank, file or diagonal.
+pub fn between(a: usize, b: usize) -> Bitboard {
+    let (bit_a, bit_b): (Bitboard, Bitboard) = (1 << a, 1 << b);
+
+    //each slider stops at the other, so their attacks only overlap in between
+    if rook_attacks(a, 0) & bit_b != 0 {
+        rook_attacks(a, bit_b) & rook_attacks(b, bit_a)
+    } else if bishop_attacks(a, 0) & bit_b != 0 {
+        bishop_attacks(a, bit_b) & bishop_attacks(b, bit_a)
+    } else {
+        0
+    }
+}
+
+/// Builds the sliding attack tables now, rather than on first use.
+pub fn init() {
+    tables();
+}
+
+/// Works out every square reachable by single jumps from each square.
+const fn leaper_table(offsets: &[(isize, isize)]) -> [Bitboard; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+
+    while square < 64 {
+        let mut i = 0;
+
+        while i < offsets.len() {
+            let row = (square / 8) as isize + offsets[i].0;
+            let col = (square % 8) as isize + offsets[i].1;
+
+            if row >= 0 && row < 8 && col >= 0 && col < 8 {
+                table[square] |= 1 << (row * 8 + col);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+
+    table
+}
+
+/// Walks each ray from the square until it leaves the board or hits a piece.
+/// 
+/// This is the slow, obviously correct way to find sliding attacks,
+/// used to fill in the magic tables.
+fn slide(square: usize, occupied: Bitboard, directions: &[(isize, isize)]) -> Bitboard {
+    let mut attacks = 0;
+
+    for &(dr, dc) in directions {
+        let (mut row, mut col) = ((square / 8) as isize, (square % 8) as isize);
+
+        loop {
+            row += dr;
+            col += dc;
+            if !(0..8).contains(&row) || !(0..8).contains(&col) {
+                break
+            }
+
+            let bit = 1 << (row * 8 + col);
+            attacks |= bit;
+            if occupied & bit != 0 {
+                break
+            }
+        }
+    }
+
+    attacks
+}
+
+/// Returns the squares along each ray whose occupancy can block a slider.
+/// The last square of each ray is left out, as nothing lies beyond it to block.
+fn relevant_blockers(square: usize, directions: &[(isize, isize)]) -> Bitboard {
+    let mut mask = 0;
+
+    for &(dr, dc) in directions {
+        let (mut row, mut col) = ((square / 8) as isize, (square % 8) as isize);
+
+        loop {
+            row += dr;
+            col += dc;
+            let (next_row, next_col) = (row + dr, col + dc);
+            if !(0..8).contains(&next_row) || !(0..8).contains(&next_col) {
+                break
+            }
+
+            mask |= 1 << (row * 8 + col);
+        }
+    }
+
+    mask
+}
+
+/// How to find one square's slider attacks in the shared table.
+#[derive(Clone, Copy, Default)]
+struct Magic {
+    mask: Bitboard,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    #[inline]
+    fn index(&self, occupied: Bitboard) -> usize {
+        let blockers = occupied & self.mask;
+        self.offset + (blockers.wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+struct Tables {
+    rook: [Magic; 64],
+    bishop: [Magic; 64],
+    attacks: Vec<Bitboard>,
+}
+
+static TABLES: OnceLock<Tables> = OnceLock::new();
+
+#[inline]
+fn tables() -> &'static Tables {
+    TABLES.get_or_init(|| {
+        let mut attacks = Vec::new();
+        let rook = fill(&mut attacks, &ROOK_MAGICS, &ORTHOGONALS);
+        let bishop = fill(&mut attacks, &BISHOP_MAGICS, &DIAGONALS);
+
+        Tables {rook, bishop, attacks}
+    })
+}
+
+/// Appends one slider's attacks for every square and blocker arrangement
+/// to the table, returning where to find them.
+fn fill(attacks: &mut Vec<Bitboard>, magics: &[u64; 64], directions: &[(isize, isize)]) -> [Magic; 64] {
+    let mut entries = [Magic::default(); 64];
+
+    for (square, entry) in entries.iter_mut().enumerate() {
+        let mask = relevant_blockers(square, directions);
+        let bits = mask.count_ones();
+
+        *entry = Magic {
+            mask,
+            magic: magics[square],
+            shift: 64 - bits,
+            offset: attacks.len(),
+        };
+        attacks.resize(attacks.len() + (1 << bits), 0);
+
+        //visit every subset of the mask, carrying through the unset bits
+        let mut blockers: Bitboard = 0;
+        loop {
+            let index = entry.index(blockers);
+            let slid = slide(square, blockers, directions);
+
+            //two arrangements may only share a slot if they give the same attacks
+            assert!(attacks[index] == 0 || attacks[index] == slid, "bad magic for square {}", square);
+            attacks[index] = slid;
+
+            blockers = blockers.wrapping_sub(mask) & mask;
+            if blockers == 0 {
+                break
+            }
+        }
+    }
+
+    entries
+}
+
+// Found by trying sparse random numbers until one indexed every blocker
+// arrangement without two different attack sets colliding.
+#[rustfmt::skip]
+const ROOK_MAGICS: [u64; 64] = [
+    0xc080009128c00280, 0x0a80200080400410, 0x4080100480082000, 0x0080100008008005,
+    0x0600020010040820, 0x1300080400010022, 0x0880020000802100, 0x0100010008205082,
+    0x0020800020804004, 0x0100400040201000, 0x0104802000100280, 0x2001000810002100,
+    0x2400800800800401, 0x0102000200041008, 0x0105000600150004, 0x8224802100084080,
+    0x0110808000304002, 0x1110004000402000, 0x108c110041002000, 0x9800808010000800,
+    0x0002050011000800, 0x0044808004000200, 0x000a004040010080, 0xc01002000881204c,
+    0x0080004040002002, 0x0040200080400088, 0x0020010100201048, 0x2100102200420008,
+    0x1102050100080010, 0x4400040080020080, 0x1050100400880201, 0xb110344200048104,
+    0x0080004001402000, 0x0000802008804000, 0x0000802002801000, 0x0280800800801001,
+    0x0801040081801800, 0x0040020080800400, 0x0142008162000804, 0x0800030842000194,
+    0x3220800040008020, 0x0000200040008080, 0x0002008010420020, 0x0090100008008080,
+    0x8011008800050010, 0x0206000488120030, 0x2420019008040002, 0x4018011844820004,
+    0x01084007a8800480, 0x0502201002400440, 0x0802802001100480, 0x0010000800148080,
+    0x0048000402004140, 0x0811000400024900, 0x1000018810020400, 0x0004004108840600,
+    0x3300218001164101, 0x28002c4000801101, 0x2004102001010841, 0x8902004020080412,
+    0x2001000800021005, 0x00a2001430411802, 0x100a104100a20804, 0x0000190400224082,
+];
+
+#[rustfmt::skip]
+const BISHOP_MAGICS: [u64; 64] = [
+    0x412302020a040101, 0x024b0c0822004200, 0x0010240080200001, 0x8042408100a28040,
+    0x0101114005408090, 0x8002012520800802, 0x0109041004040000, 0x840a908805101040,
+    0x042020090210a400, 0x1800202a024a4100, 0x0904040802084014, 0x9610024081004400,
+    0x0818041029200200, 0x0003020130492002, 0x0004004210d00800, 0x23030a0211140200,
+    0xa028104043480a00, 0x4058482108450340, 0x0034002088048068, 0x0004040840400800,
+    0x4008860c00a00400, 0x260a000122100a00, 0x8804e00413080801, 0x404d000094008201,
+    0x4034050022081002, 0x803004001004a080, 0x0104060030083040, 0x0a22008008008002,
+    0x8081001005004023, 0x0910010010241100, 0x0102140022008280, 0x000089000a010090,
+    0x8548344044105208, 0x0242021108825003, 0x000a010400404042, 0x4018020080080082,
+    0x8041080200002200, 0x0611100081010800, 0x8801020884020820, 0x2081004080050400,
+    0x8002026104092000, 0x000201042100a282, 0x2416010402080100, 0x20c0804204804805,
+    0x4401400812008040, 0x0229291000800100, 0x01425a2421088c00, 0x2002040402280680,
+    0x0004008809882020, 0x3000c20210362004, 0x11800a3508080000, 0x0200200820880000,
+    0xe000801082020801, 0x2000401002c28400, 0x0005200802008080, 0x200802408c0b0802,
+    0x08b2804410340240, 0x2048704058080800, 0x0140100215460800, 0x0048000504840420,
+    0x0000028210202210, 0x8000000810211200, 0x20000a1081080904, 0x0450010808228420,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pieces::Position;
+
+    fn sq(name: &str) -> usize {
+        let pos = Position::from_square(name).unwrap();
+        pos.row * 8 + pos.col
+    }
+
+    fn bits(names: &[&str]) -> Bitboard {
+        names.iter().fold(0, |bb, name| bb | 1 << sq(name))
+    }
+
+    #[test]
+    fn test_leaper_attacks() {
+        assert_eq!(knight_attacks(sq("a1")), bits(&["b3", "c2"]));
+        assert_eq!(knight_attacks(sq("e4")).count_ones(), 8);
+        assert_eq!(king_attacks(sq("h8")), bits(&["g8", "g7", "h7"]));
+        assert_eq!(pawn_attacks(sq("e4"), Colour::White), bits(&["d5", "f5"]));
+        assert_eq!(pawn_attacks(sq("a7"), Colour::Black), bits(&["b6"]));
+        assert_eq!(pawn_attacks(sq("e8"), Colour::White), 0);
+    }
+
+    #[test]
+    fn test_slider_attacks() {
+        let occupied = bits(&["e6", "c4", "g2"]);
+
+        assert_eq!(rook_attacks(sq("e4"), occupied), bits(&[
+            "e5", "e6", "e3", "e2", "e1", "d4", "c4", "f4", "g4", "h4",
+        ]));
+        assert_eq!(bishop_attacks(sq("e4"), occupied), bits(&[
+            "d5", "c6", "b7", "a8", "f5", "g6", "h7", "d3", "c2", "b1", "f3", "g2",
+        ]));
+        assert_eq!(queen_attacks(sq("a1"), 0).count_ones(), 21);
+    }
+
+    #[test]
+    fn test_between() {
+        assert_eq!(between(sq("a1"), sq("a4")), bits(&["a2", "a3"]));
+        assert_eq!(between(sq("h8"), sq("c3")), bits(&["g7", "f6", "e5", "d4"]));
+        assert_eq!(between(sq("e4"), sq("f4")), 0);
+        assert_eq!(between(sq("a1"), sq("b3")), 0);
+    }
+
+    #[test]
+    fn test_magics_match_ray_walk() {
+        //a fixed xorshift sequence gives a spread of blocker arrangements
+        let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut random = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for square in 0..64 {
+            for _ in 0..200 {
+                let occupied = random() & random();
+
+                assert_eq!(rook_attacks(square, occupied), slide(square, occupied, &ORTHOGONALS));
+                assert_eq!(bishop_attacks(square, occupied), slide(square, occupied, &DIAGONALS));
+            }
+        }
+    }
+}
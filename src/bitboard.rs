@@ -0,0 +1,88 @@
+//! Bitboards: sets of squares packed into a `u64`, one bit per square.
+//! 
+//! Square indices run from a1 = 0 along each rank to h8 = 63,
+//! so the square at `board[row][col]` is bit `row * 8 + col`.
+
+use crate::pieces::Position;
+
+/// A set of squares.
+pub type Bitboard = u64;
+
+/// The light squares: b1, d1, ..., a2, c2 and so on.
+pub const LIGHT_SQUARES: Bitboard = 0x55aa_55aa_55aa_55aa;
+
+/// Returns the index of the square at the given row and column.
+#[inline]
+pub fn square(row: usize, col: usize) -> usize {
+    row * 8 + col
+}
+
+/// Returns the bitboard with only the given square set.
+#[inline]
+pub fn bit(pos: Position) -> Bitboard {
+    1 << square(pos.row, pos.col)
+}
+
+/// Returns the position of the square with the given index.
+#[inline]
+pub fn position(square: usize) -> Position {
+    Position {row: square / 8, col: square % 8}
+}
+
+/// Iterates over the squares in a bitboard, from a1 towards h8.
+pub fn squares(bb: Bitboard) -> Squares {
+    Squares(bb)
+}
+
+/// An iterator over the squares set in a bitboard.
+#[derive(Clone, Copy, Debug)]
+pub struct Squares(Bitboard);
+
+impl Iterator for Squares {
+    type Item = Position;
+
+    fn next(&mut self) -> Option<Position> {
+        if self.0 == 0 {
+            return None
+        }
+
+        let square = self.0.trailing_zeros() as usize;
+        //clear the lowest set bit
+        self.0 &= self.0 - 1;
+
+        Some(position(square))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Squares {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_squares() {
+        let e4 = Position::from_square("e4").unwrap();
+        assert_eq!(bit(e4), 1 << 28);
+        assert_eq!(position(28), e4);
+
+        let found: Vec<_> = squares(bit(e4) | 1 | 1 << 63).map(|p| p.to_string()).collect();
+        assert_eq!(found, vec!["a1", "e4", "h8"]);
+        assert_eq!(squares(0).len(), 0);
+    }
+
+    #[test]
+    fn test_light_squares() {
+        for sq in 0..64 {
+            let pos = position(sq);
+            let light = (pos.row + pos.col) % 2 == 1;
+
+            assert_eq!(LIGHT_SQUARES & 1 << sq != 0, light, "{}", pos);
+        }
+    }
+}
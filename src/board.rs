@@ -2,26 +2,58 @@ use std::fmt;
 use std::error::Error;
 use std::ops::{Index, IndexMut};
 
+use crate::attacks;
+use crate::bitboard::{self, Bitboard, LIGHT_SQUARES};
 use crate::pieces::*;
-use crate::moves::{Move, MoveMarker};
+use crate::moves::{Move, MoveMarker, MoveSet};
+use crate::zobrist;
 
+#[derive(Clone)]
 pub struct Board {
-    pub board: [[PieceType; 8]; 8],
+    // the piece on each square, which the bitboards below mirror
+    board: [[PieceType; 8]; 8],
+    // the squares occupied by each kind of piece and by each colour,
+    // indexed in the declaration order of PieceKind and Colour
+    kinds: [Bitboard; 6],
+    colours: [Bitboard; 2],
     turn: Colour,
     // indicates which king is being checked
     check: Option<Colour>,
+    castling: CastlingRights,
+    // the square a pawn skipped over with a double push last move
+    en_passant: Option<Position>,
+    // plies since the last capture or pawn move, for the fifty-move rule
+    halfmove_clock: u32,
+    // starts at 1 and increments after every black move
+    fullmove_number: u32,
     white_cap: Vec<PieceType>,
     black_cap: Vec<PieceType>,
+    history: Vec<MoveRecord>,
+    // the length of the history when the side to move last passed with
+    // `play_null`, while that pass is still on the board
+    null_at: Option<usize>,
+    // zobrist hash of everything but the en passant square,
+    // whose part depends on the pawns around it
+    hash: u64,
 }
 
 impl Board {
     pub fn empty() -> Self {
         Board{
             board: [[PieceType::Empty; 8]; 8],
+            kinds: [0; 6],
+            colours: [0; 2],
             turn: Colour::White,
             check: None,
+            castling: CastlingRights::none(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
             white_cap: Vec::new(),
             black_cap: Vec::new(),
+            history: Vec::new(),
+            null_at: None,
+            hash: zobrist::turn(Colour::White),
         }
     }
 
@@ -38,65 +70,73 @@ impl Board {
         let is_empty = self.is_empty();
 
         //initializing rooks
-        self.board[0][0] = PieceType::Rook(Rook::config(0, 0, White));
+        self.put(0, 0, PieceType::Rook(Rook::config(0, 0, White)));
 
-        self.board[0][7] = PieceType::Rook(Rook::config(0, 7, White));
+        self.put(0, 7, PieceType::Rook(Rook::config(0, 7, White)));
 
-        self.board[7][0] = PieceType::Rook(Rook::config(7, 0, Black));
+        self.put(7, 0, PieceType::Rook(Rook::config(7, 0, Black)));
 
-        self.board[7][7] = PieceType::Rook(Rook::config(7, 7, Black));
+        self.put(7, 7, PieceType::Rook(Rook::config(7, 7, Black)));
 
         //initializing knights
-        self.board[0][1] = PieceType::Knight(Knight::config(0, 1, White));
+        self.put(0, 1, PieceType::Knight(Knight::config(0, 1, White)));
 
-        self.board[0][6] = PieceType::Knight(Knight::config(0, 6, White));
+        self.put(0, 6, PieceType::Knight(Knight::config(0, 6, White)));
 
-        self.board[7][1] = PieceType::Knight(Knight::config(7, 1, Black));
+        self.put(7, 1, PieceType::Knight(Knight::config(7, 1, Black)));
 
-        self.board[7][6] = PieceType::Knight(Knight::config(7, 6, Black));
+        self.put(7, 6, PieceType::Knight(Knight::config(7, 6, Black)));
 
         //initializing bishops
-        self.board[0][2] = PieceType::Bishop(Bishop::config(0, 2, White));
+        self.put(0, 2, PieceType::Bishop(Bishop::config(0, 2, White)));
 
-        self.board[0][5] = PieceType::Bishop(Bishop::config(0, 5, White));
+        self.put(0, 5, PieceType::Bishop(Bishop::config(0, 5, White)));
 
-        self.board[7][2] = PieceType::Bishop(Bishop::config(7, 2, Black));
+        self.put(7, 2, PieceType::Bishop(Bishop::config(7, 2, Black)));
 
-        self.board[7][5] = PieceType::Bishop(Bishop::config(7, 5, Black));
+        self.put(7, 5, PieceType::Bishop(Bishop::config(7, 5, Black)));
 
         //initializing queens and kings
-        self.board[0][3] = PieceType::Queen(Queen::config(0, 3, White));
+        self.put(0, 3, PieceType::Queen(Queen::config(0, 3, White)));
 
-        self.board[0][4] = PieceType::King(King::config(0, 4, White));
+        self.put(0, 4, PieceType::King(King::config(0, 4, White)));
 
-        self.board[7][3] = PieceType::Queen(Queen::config(7, 3, Black));
+        self.put(7, 3, PieceType::Queen(Queen::config(7, 3, Black)));
 
-        self.board[7][4] = PieceType::King(King::config(7, 4, Black));
+        self.put(7, 4, PieceType::King(King::config(7, 4, Black)));
 
         //initializing pawns
         for i in 0..8 {
-            self.board[1][i] = PieceType::Pawn(
-                Pawn::config(1, i, White)
-            );
-
-            self.board[6][i] = PieceType::Pawn(
-                Pawn::config(6, i, Black)
-            );
+            self.put(1, i, PieceType::Pawn(Pawn::config(1, i, White)));
+            self.put(6, i, PieceType::Pawn(Pawn::config(6, i, Black)));
         }
 
         if !is_empty {
-            for i in 2..5 {
-                for j in 0..7 {
-                    self.board[i][j] = PieceType::Empty;
+            for i in 2..6 {
+                for j in 0..8 {
+                    self.put(i, j, PieceType::Empty);
                 }
             }
         }
+
+        self.turn = White;
+        self.castling = CastlingRights::all();
+        self.en_passant = None;
+        self.halfmove_clock = 0;
+        self.fullmove_number = 1;
+        self.white_cap.clear();
+        self.black_cap.clear();
+        self.history.clear();
+        self.hash = self.compute_hash();
     }
 
+    /// Plays a legal move for the side to move and passes the turn.
+    /// 
+    /// Returns an error without touching the board if the move is
+    /// not legal in the current position.
     pub fn move_piece(&mut self, mov: Move) -> Result<(), GameError> {
-        //moving the piece at origin to a temp variable
-        let temp = self.board[mov.origin().0][mov.origin().1];
-        let targ = self.board[mov.target().1][mov.target().1];
+        let o = mov.origin();
+        let temp = self.board[o.0][o.1];
 
         //checking that the origin is actually not empty
         if let Some(colour) = temp.colour() {
@@ -108,18 +148,237 @@ impl Board {
             return Err(GameError::EmptySpace)
         }
 
+        if !self.legal_moves().contains(&mov) {
+            return Err(GameError::InvalidMove)
+        }
+
+        self.play(mov);
+
+        Ok(())
+    }
+
+    /// Plays a move without checking that it is legal, and passes the turn.
+    /// 
+    /// The move must come from `legal_moves` for this exact position,
+    /// otherwise the board may end up in an impossible state.
+    /// It is logged like any other move, so it can be taken back.
+    pub(crate) fn play(&mut self, mov: Move) {
+        //moving the piece at origin to a temp variable
+        let (o, t) = (mov.origin(), mov.target());
+        let temp = self.board[o.0][o.1];
+        let targ = self.board[t.0][t.1];
+        let record = self.record(temp, mov);
+
         //check if target is empty; if not, add target to captures
         if !targ.is_empty() {
             self.add_to_captures(self.turn, targ)
         }
 
+        //a pawn moving diagonally onto an empty square is capturing en passant,
+        //and the captured pawn sits beside it on the origin rank
+        if temp.is_pawn() && targ.is_empty() && o.1 != t.1 {
+            self.add_to_captures(self.turn, record.captured);
+            self.put(o.0, t.1, PieceType::Empty);
+        }
+
         //making the actual move
-        self.board[mov.origin().0][mov.origin().1] = PieceType::Empty;
-        self.board[mov.target().0][mov.target().1] = temp;
+        self.put(o.0, o.1, PieceType::Empty);
+        self.put(t.0, t.1, match mov.promotion() {
+            Some(kind) => PieceType::from_kind(kind, t.0, t.1, self.turn),
+            None => temp.moved_to(t.0, t.1),
+        });
+
+        //a double pawn push leaves the skipped square open to en passant
+        self.en_passant = if temp.is_pawn() && (t.0 as isize - o.0 as isize).abs() == 2 {
+            Some(Position {row: (o.0 + t.0) / 2, col: o.1})
+        } else {
+            None
+        };
 
-        self.log_move(temp, mov);
+        //a king moving two files is castling, so bring the rook across
+        if temp.is_king() && (t.1 as isize - o.1 as isize).abs() == 2 {
+            let (rook_from, rook_to) = if t.1 == 6 { (7, 5) } else { (0, 3) };
+            let rook = self.board[o.0][rook_from];
 
-        Ok(())
+            self.put(o.0, rook_from, PieceType::Empty);
+            self.put(o.0, rook_to, rook.moved_to(o.0, rook_to));
+        }
+
+        self.hash ^= zobrist::castling(self.castling);
+        self.castling.revoke_touching(o);
+        self.castling.revoke_touching(t);
+        self.hash ^= zobrist::castling(self.castling);
+
+        if temp.is_pawn() || !targ.is_empty() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if self.turn == Colour::Black {
+            self.fullmove_number += 1;
+        }
+
+        self.flip_turn();
+        self.history.push(record);
+    }
+
+    /// Takes back the last move played, restoring the position exactly
+    /// as it was before, and returns the move.
+    /// 
+    /// Returns None if no moves have been played.
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let record = self.history.pop()?;
+        let (o, t) = (record.mov.origin(), record.mov.target());
+
+        self.flip_turn();
+
+        //the recorded piece still carries its origin square
+        self.put(o.0, o.1, record.piece);
+        self.put(t.0, t.1, PieceType::Empty);
+
+        if !record.captured.is_empty() {
+            let (row, col) = record.captured.inner()
+                .unwrap()
+                .position()
+                .as_tuple();
+            self.put(row, col, record.captured);
+
+            match self.turn {
+                Colour::White => self.white_cap.pop(),
+                Colour::Black => self.black_cap.pop(),
+            };
+        }
+
+        if record.piece.is_king() && (t.1 as isize - o.1 as isize).abs() == 2 {
+            let (rook_from, rook_to) = if t.1 == 6 { (7, 5) } else { (0, 3) };
+            let rook = self.board[o.0][rook_to];
+
+            self.put(o.0, rook_to, PieceType::Empty);
+            self.put(o.0, rook_from, rook.moved_to(o.0, rook_from));
+        }
+
+        self.castling = record.castling;
+        self.en_passant = record.en_passant;
+        self.halfmove_clock = record.halfmove_clock;
+        self.fullmove_number = record.fullmove_number;
+        self.hash = record.hash ^ self.en_passant_hash();
+
+        Some(record.mov)
+    }
+
+    /// Passes the turn without moving, for null-move pruning in the search.
+    /// 
+    /// The pass is not logged, so it must be taken back with `unmake_null`
+    /// before any other move is unmade.
+    pub(crate) fn play_null(&mut self) -> NullMove {
+        let undo = NullMove {
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            hash: self.hash,
+            null_at: self.null_at,
+        };
+        self.null_at = Some(self.history.len());
+
+        //no position before the pass can be repeated after it
+        self.en_passant = None;
+        self.halfmove_clock = 0;
+        self.flip_turn();
+
+        undo
+    }
+
+    /// Takes back a pass made with `play_null`.
+    pub(crate) fn unmake_null(&mut self, undo: NullMove) {
+        self.turn = self.turn.opposite();
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.hash = undo.hash;
+        self.null_at = undo.null_at;
+    }
+
+    /// Places a piece on the board at the position it was configured with,
+    /// replacing whatever was there.
+    pub fn place(&mut self, piece: PieceType) {
+        let (row, col) = piece.inner()
+            .expect("Cannot place an empty piece")
+            .position()
+            .as_tuple();
+
+        self.put(row, col, piece);
+    }
+
+    /// Empties a square, returning the piece that stood there.
+    pub fn remove(&mut self, row: usize, col: usize) -> PieceType {
+        let piece = self.board[row][col];
+        self.put(row, col, PieceType::Empty);
+
+        piece
+    }
+
+    /// Brings the bitboards and hash back in line with the squares
+    /// after they have been written to through `IndexMut`.
+    pub fn resync(&mut self) {
+        let board = self.board;
+
+        self.kinds = [0; 6];
+        self.colours = [0; 2];
+        self.board = [[PieceType::Empty; 8]; 8];
+        self.hash = zobrist::castling(self.castling) ^ zobrist::turn(self.turn);
+
+        for (row, pieces) in board.iter().enumerate() {
+            for (col, piece) in pieces.iter().enumerate() {
+                self.put(row, col, *piece);
+            }
+        }
+    }
+
+    /// Puts a piece on a square, keeping the bitboards and hash in step.
+    /// Every change to the board goes through here.
+    fn put(&mut self, row: usize, col: usize, piece: PieceType) {
+        let old = self.board[row][col];
+        let bit: Bitboard = 1 << bitboard::square(row, col);
+
+        if let (Some(kind), Some(colour)) = (old.kind(), old.colour()) {
+            self.kinds[kind as usize] &= !bit;
+            self.colours[colour as usize] &= !bit;
+        }
+        if let (Some(kind), Some(colour)) = (piece.kind(), piece.colour()) {
+            self.kinds[kind as usize] |= bit;
+            self.colours[colour as usize] |= bit;
+        }
+
+        self.hash ^= zobrist::piece(old, row, col) ^ zobrist::piece(piece, row, col);
+        self.board[row][col] = piece;
+    }
+
+    /// Returns the squares holding pieces of the given kind and colour.
+    #[inline]
+    pub fn pieces(&self, kind: PieceKind, colour: Colour) -> Bitboard {
+        self.kinds[kind as usize] & self.colours[colour as usize]
+    }
+
+    /// Returns the squares holding pieces of the given kind, of either colour.
+    #[inline]
+    pub fn pieces_of_kind(&self, kind: PieceKind) -> Bitboard {
+        self.kinds[kind as usize]
+    }
+
+    /// Returns the squares holding pieces of the given colour.
+    #[inline]
+    pub fn pieces_of_colour(&self, colour: Colour) -> Bitboard {
+        self.colours[colour as usize]
+    }
+
+    /// Returns the squares holding any piece.
+    #[inline]
+    pub fn occupied(&self) -> Bitboard {
+        self.colours[0] | self.colours[1]
+    }
+
+    /// Returns the squares as a grid of pieces, indexed `[row][col]`.
+    #[inline]
+    pub fn grid(&self) -> &[[PieceType; 8]; 8] {
+        &self.board
     }
 
     pub fn add_to_captures(&mut self, colour: Colour, piece: PieceType) {
@@ -138,12 +397,180 @@ impl Board {
         }
     }
 
-    pub fn log_move(&mut self, piece: PieceType, _mov: Move) {
+    /// Records in the history that `piece` makes the given move, without
+    /// making it, for moves played by editing the squares directly.
+    /// 
+    /// Call it before the edits, since the record keeps the position as it
+    /// stands for `unmake_move` to restore. Moves made with `move_piece`
+    /// are recorded already.
+    pub fn log_move(&mut self, piece: PieceType, mov: Move) {
         assert!(!piece.is_empty());
-        unimplemented!("logging moves unimplemented")
+        let record = self.record(piece, mov);
+        self.history.push(record)
+    }
+
+    /// Builds the record needed to take back the given move, before it is made.
+    fn record(&self, piece: PieceType, mov: Move) -> MoveRecord {
+        let (o, t) = (mov.origin(), mov.target());
+        let mut captured = self.board[t.0][t.1];
+
+        //en passant takes the pawn beside the target square, not on it
+        if piece.is_pawn() && captured.is_empty() && o.1 != t.1 {
+            captured = self.board[o.0][t.1];
+        }
+
+        MoveRecord {
+            mov,
+            piece,
+            captured,
+            castling: self.castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            hash: self.hash(),
+        }
+    }
+
+    /// Returns the move that led to this position, or None at the start
+    /// of the history or straight after a pass made with `play_null`.
+    pub fn previous_move(&self) -> Option<Move> {
+        if self.null_at == Some(self.history.len()) {
+            return None
+        }
+
+        self.history.last().map(|record| record.mov)
+    }
+
+    /// Returns every move played so far, oldest first.
+    #[inline]
+    pub fn history(&self) -> &[MoveRecord] {
+        &self.history
+    }
+
+    /// Returns the colour whose turn it is to move.
+    #[inline]
+    pub fn turn(&self) -> Colour {
+        self.turn
+    }
+
+    /// Returns the castling rights remaining to both sides.
+    #[inline]
+    pub fn castling_rights(&self) -> CastlingRights {
+        self.castling
+    }
+
+    /// Overrides the castling rights, e.g. when setting up a position by hand.
+    #[inline]
+    pub fn set_castling_rights(&mut self, rights: CastlingRights) {
+        self.hash ^= zobrist::castling(self.castling) ^ zobrist::castling(rights);
+        self.castling = rights;
+    }
+
+    /// Returns the square that can currently be captured onto en passant, if any.
+    #[inline]
+    pub fn en_passant(&self) -> Option<Position> {
+        self.en_passant
+    }
+
+    /// Overrides the en passant square, e.g. when setting up a position by hand.
+    #[inline]
+    pub fn set_en_passant(&mut self, square: Option<Position>) {
+        self.en_passant = square;
+    }
+
+    /// Returns the number of plies since the last capture or pawn move.
+    #[inline]
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    /// Returns the number of the current full move, starting from 1.
+    #[inline]
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    /// Overrides the halfmove clock and fullmove number.
+    #[inline]
+    pub(crate) fn set_clocks(&mut self, halfmove: u32, fullmove: u32) {
+        self.halfmove_clock = halfmove;
+        self.fullmove_number = fullmove;
+    }
+
+    /// Returns whether the given square is attacked by any piece of colour `by`.
+    /// 
+    /// This only looks at which squares pieces bear on,
+    /// so it does not care whether the attacking piece is pinned.
+    pub fn is_square_attacked(&self, pos: Position, by: Colour) -> bool {
+        self.attackers_to(pos) & self.pieces_of_colour(by) != 0
+    }
+
+    /// Returns the squares of every piece, of either colour, attacking the given square.
+    pub fn attackers_to(&self, pos: Position) -> Bitboard {
+        self.attackers_with(bitboard::square(pos.row, pos.col), self.occupied())
+    }
+
+    /// Returns the pieces attacking the given square if only the pieces
+    /// in `occupied` blocked the sliders, for looking at the board with
+    /// some pieces lifted off it. Pieces outside `occupied` may still be
+    /// included, so mask the result if that matters.
+    pub(crate) fn attackers_with(&self, square: usize, occupied: Bitboard) -> Bitboard {
+        use PieceKind::*;
+
+        let diagonal = self.pieces_of_kind(Bishop) | self.pieces_of_kind(Queen);
+        let straight = self.pieces_of_kind(Rook) | self.pieces_of_kind(Queen);
+
+        //a pawn attacks a square if a pawn of the other colour there would attack it back
+        attacks::pawn_attacks(square, Colour::White) & self.pieces(Pawn, Colour::Black)
+            | attacks::pawn_attacks(square, Colour::Black) & self.pieces(Pawn, Colour::White)
+            | attacks::knight_attacks(square) & self.pieces_of_kind(Knight)
+            | attacks::king_attacks(square) & self.pieces_of_kind(King)
+            | attacks::bishop_attacks(square, occupied) & diagonal
+            | attacks::rook_attacks(square, occupied) & straight
+    }
+
+    /// Returns the enemy pieces giving check to the side to move.
+    pub fn checkers(&self) -> Bitboard {
+        self.attackers_to(self.find_king(self.turn)) & self.pieces_of_colour(self.turn.opposite())
+    }
+
+    /// Returns the pieces of the given colour that are pinned to their own king,
+    /// and so cannot leave the line between it and an enemy slider.
+    pub fn pinned_pieces(&self, colour: Colour) -> Bitboard {
+        use PieceKind::*;
+
+        let king = self.find_king(colour);
+        let king = bitboard::square(king.row, king.col);
+        let enemy = colour.opposite();
+
+        //enemy sliders that would attack the king if nothing stood in the way
+        let snipers = attacks::rook_attacks(king, 0) & (self.pieces(Rook, enemy) | self.pieces(Queen, enemy))
+            | attacks::bishop_attacks(king, 0) & (self.pieces(Bishop, enemy) | self.pieces(Queen, enemy));
+
+        bitboard::squares(snipers)
+            .map(|sniper| attacks::between(king, bitboard::square(sniper.row, sniper.col)) & self.occupied())
+            .filter(|blockers| blockers.count_ones() == 1)
+            .fold(0, |pinned, blocker| pinned | blocker & self.pieces_of_colour(colour))
+    }
+
+    /// Returns the kind of piece a move would capture, or None for a quiet move.
+    /// En passant counts as capturing a pawn.
+    pub fn captured(&self, mov: &Move) -> Option<PieceKind> {
+        let (o, t) = (mov.origin(), mov.target());
+        let target = self.board[t.0][t.1];
+
+        if !target.is_empty() {
+            target.kind()
+        } else if self.board[o.0][o.1].is_pawn() && o.1 != t.1 {
+            Some(PieceKind::Pawn)
+        } else {
+            None
+        }
     }
 
     pub fn flip_turn(&mut self) {
+        self.hash ^= zobrist::turn(Colour::White);
+
         if self.turn == Colour::Black {
             self.turn = Colour::White
         } else {
@@ -151,68 +578,201 @@ impl Board {
         }
     }
 
+    /// Returns the Zobrist hash of the position, which identifies it
+    /// for repetition checks and the transposition table.
+    /// 
+    /// It covers the pieces, side to move, castling rights and the en passant
+    /// file when a capture there is possible, so positions that are the same
+    /// for the rules of repetition hash the same.
+    #[inline]
+    pub fn hash(&self) -> u64 {
+        self.hash ^ self.en_passant_hash()
+    }
+
+    fn en_passant_hash(&self) -> u64 {
+        match self.en_passant {
+            Some(ep) if self.en_passant_capturable() => zobrist::en_passant(ep.col),
+            _ => 0,
+        }
+    }
+
+    /// Returns whether a pawn of the side to move stands beside the pawn that
+    /// just pushed two squares, ready to capture it en passant.
+    /// 
+    /// Whether the capture would be legal is not considered.
+    pub(crate) fn en_passant_capturable(&self) -> bool {
+        self.en_passant.is_some_and(|ep| {
+            let dir = match self.turn {
+                Colour::White => -1,
+                Colour::Black => 1,
+            };
+
+            [-1, 1].iter()
+                .filter_map(|&dc| ep.offset(dir, dc))
+                .any(|p| {
+                    let piece = self.board[p.row][p.col];
+                    piece.is_pawn() && piece.colour() == Some(self.turn)
+                })
+        })
+    }
+
+    /// Works out whether the game is over and why, or whether
+    /// the side to move is in check.
+    /// 
+    /// Checkmate and stalemate take precedence over the draw rules,
+    /// so a mate delivered on the hundredth ply still wins.
     pub fn eval_gamestate(&mut self) -> GameState {
-        let _kingpos = self.find_king(self.turn);
-
-        for pos in self.enum_opposite_pieces() {
-            let (row, col) = pos.as_tuple();
-
-            if self[row][col]
-                .inner().unwrap()
-                .evaluate_moves(&self)
-                .is_checking() {
-                self.check = Some(self.turn);
-                return GameState::Check(self.turn);
-                //todo: work this outtt
+        let in_check = self.checkers() != 0;
+
+        self.check = if in_check { Some(self.turn) } else { None };
+
+        if self.legal_moves().is_empty() {
+            return if in_check {
+                GameState::Checkmate(self.turn)
+            } else {
+                GameState::Stalemate
             }
         }
 
-        unimplemented!()
+        if self.halfmove_clock >= 100 {
+            return GameState::FiftyMoveRule
+        }
+        if self.repetitions() >= 3 {
+            return GameState::ThreefoldRepetition
+        }
+        if self.insufficient_material() {
+            return GameState::InsufficientMaterial
+        }
+
+        if in_check {
+            GameState::Check(self.turn)
+        } else {
+            GameState::None
+        }
+    }
+
+    /// Returns whether the current position has occurred before
+    /// since the last capture or pawn move.
+    pub(crate) fn is_repetition(&self) -> bool {
+        self.repetitions() > 1
     }
 
-    fn enum_opposite_pieces(&self) -> Vec<Position> {
+    /// Counts how many times the current position has occurred,
+    /// including now.
+    /// 
+    /// Only positions since the last capture or pawn move can repeat,
+    /// so the search goes no further back than the halfmove clock.
+    fn repetitions(&self) -> usize {
+        let hash = self.hash();
+        let plies = (self.halfmove_clock as usize).min(self.history.len());
+
+        1 + self.history.iter()
+            .rev()
+            .take(plies)
+            .filter(|record| record.hash == hash)
+            .count()
+    }
 
-        let mut positions = Vec::new();
+    /// Returns whether neither side has enough material left to ever checkmate:
+    /// bare kings, a single minor piece, or only bishops all on one square colour.
+    fn insufficient_material(&self) -> bool {
+        use PieceKind::*;
 
-        for i in 0..8 {
-            for j in 0..8 {
-                if let Some(colour) = self[i][j].colour() {
-                    if colour != self.turn {
-                        positions.push(self[i][j]
-                            .inner()
-                            // Unwrap should never panic because colour is not None
-                            .unwrap()
-                            .position())
-                    }
+        let heavy = self.pieces_of_kind(Pawn) | self.pieces_of_kind(Rook) | self.pieces_of_kind(Queen);
+        if heavy != 0 {
+            return false
+        }
+
+        let knights = self.pieces_of_kind(Knight);
+        let bishops = self.pieces_of_kind(Bishop);
+        let single_bishop_colour = bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0;
+
+        (knights | bishops).count_ones() <= 1 || (knights == 0 && single_bishop_colour)
+    }
+
+    /// Returns every legal move for the side to move.
+    /// 
+    /// Each piece's pseudo-legal moves are kept if they cannot leave the
+    /// mover's own king attacked. Only king moves, moves by pinned pieces,
+    /// en passant and moves made while in check can do so; those are
+    /// checked by lifting the pieces off the board and looking again.
+    /// Castling through check is already refused by the king itself.
+    pub fn legal_moves(&self) -> MoveSet {
+        let in_check = self.checkers() != 0;
+        let pinned = self.pinned_pieces(self.turn);
+        let mut moves = Vec::new();
+
+        for pos in self.enum_pieces(self.turn) {
+            let piece = self[pos.row][pos.col];
+
+            let pseudo = piece.inner()
+                // Unwrap should never panic because enum_pieces skips empty squares
+                .unwrap()
+                .evaluate_moves(self);
+
+            let needs_check = in_check || piece.is_king() || pinned & bitboard::bit(pos) != 0;
+
+            for mov in pseudo {
+                let t = mov.target();
+                let en_passant = piece.is_pawn() && pos.col != t.1 && self.board[t.0][t.1].is_empty();
+
+                if !(needs_check || en_passant) || self.keeps_king_safe(mov) {
+                    moves.push(mov);
                 }
             }
         }
 
-        positions
+        MoveSet::from(moves)
     }
 
-    pub(crate) fn find_king(&self, c: Colour) -> Position {
+    /// Returns whether the side to move's king would be safe after the move.
+    /// 
+    /// Only the pieces are moved, on a copy of the occupancy;
+    /// turn, castling and en passant state are untouched.
+    fn keeps_king_safe(&self, mov: Move) -> bool {
+        let (o, t) = (mov.origin(), mov.target());
+        let piece = self.board[o.0][o.1];
+        let target = bitboard::square(t.0, t.1);
+
+        let mut occupied = self.occupied() & !(1 << bitboard::square(o.0, o.1)) | 1 << target;
+        let mut captured: Bitboard = 1 << target;
+
+        if piece.is_pawn() && o.1 != t.1 && self.board[t.0][t.1].is_empty() {
+            let behind: Bitboard = 1 << bitboard::square(o.0, t.1);
+            occupied &= !behind;
+            captured |= behind;
+        }
 
-        for i in 0..8 {
-            for j in 0..8 {
-                if let PieceType::King(k) = self[i][j] {
-                    if k.colour() == c {
-                        return k.position()
-                    }
-                }
-            }
+        if piece.is_king() && (t.1 as isize - o.1 as isize).abs() == 2 {
+            let (rook_from, rook_to) = if t.1 == 6 { (7, 5) } else { (0, 3) };
+            occupied = occupied & !(1 << bitboard::square(o.0, rook_from)) | 1 << bitboard::square(o.0, rook_to);
         }
 
-        panic!("Could not find king of colour {:?}", c)
+        let king = if piece.is_king() {
+            target
+        } else {
+            let king = self.find_king(self.turn);
+            bitboard::square(king.row, king.col)
+        };
+        let enemies = self.pieces_of_colour(self.turn.opposite()) & !captured;
+
+        self.attackers_with(king, occupied) & enemies == 0
+    }
+
+    fn enum_pieces(&self, colour: Colour) -> Vec<Position> {
+        bitboard::squares(self.pieces_of_colour(colour)).collect()
+    }
+
+    pub(crate) fn find_king(&self, c: Colour) -> Position {
+        match bitboard::squares(self.pieces(PieceKind::King, c)).next() {
+            Some(pos) => pos,
+            None => panic!("Could not find king of colour {:?}", c),
+        }
     }
 
     #[inline]
     pub fn is_in_check(&self) -> bool {
-        if let Some(_) = self.check {
-            true
-        } else {
-            false
-        }
+        self.check.is_some()
     }
 
     #[inline]
@@ -226,11 +786,7 @@ impl Board {
     }
 
     pub fn is_empty(&self) -> bool {
-        self.board.iter()
-            .all(|row| row.iter().all(
-                |col| *col == PieceType::Empty
-            )
-        )
+        self.occupied() == 0
     }
 }
 
@@ -260,6 +816,9 @@ impl Index<usize> for Board {
     }
 }
 
+/// Writes through here change the squares alone, leaving the bitboards
+/// and hash behind until `resync` is called. `place` and `remove`
+/// keep everything in step as they go, and are to be preferred.
 impl IndexMut<usize> for Board {
 
     #[inline]
@@ -268,8 +827,113 @@ impl IndexMut<usize> for Board {
     }
 }
 
+/// What `Board::play_null` changes, to restore it.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct NullMove {
+    en_passant: Option<Position>,
+    halfmove_clock: u32,
+    hash: u64,
+    null_at: Option<usize>,
+}
+
+/// Everything needed to take back a move exactly.
+#[derive(Clone, Copy, Debug)]
+pub struct MoveRecord {
+    /// The move that was played.
+    pub mov: Move,
+    /// The piece that moved, as it stood on its origin square.
+    pub piece: PieceType,
+    /// The piece that was captured, or Empty.
+    /// For en passant this is the pawn beside the target square.
+    pub captured: PieceType,
+    /// The castling rights before the move.
+    pub castling: CastlingRights,
+    /// The en passant square before the move.
+    pub en_passant: Option<Position>,
+    /// The halfmove clock before the move.
+    pub halfmove_clock: u32,
+    /// The fullmove number before the move.
+    pub fullmove_number: u32,
+    /// The hash of the position before the move.
+    pub hash: u64,
+}
+
+/// Records which castling moves each side still has the right to make.
+/// 
+/// A right is lost for good once the king or the corresponding rook
+/// has moved, or the rook has been captured on its home square.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CastlingRights {
+    pub white_kingside: bool,
+    pub white_queenside: bool,
+    pub black_kingside: bool,
+    pub black_queenside: bool,
+}
+
+impl CastlingRights {
+    /// Both sides may castle either way, as at the start of a game.
+    pub fn all() -> Self {
+        CastlingRights {
+            white_kingside: true,
+            white_queenside: true,
+            black_kingside: true,
+            black_queenside: true,
+        }
+    }
+
+    /// Neither side may castle.
+    pub fn none() -> Self {
+        CastlingRights {
+            white_kingside: false,
+            white_queenside: false,
+            black_kingside: false,
+            black_queenside: false,
+        }
+    }
+
+    /// Returns whether the given colour may still castle kingside.
+    #[inline]
+    pub fn kingside(&self, colour: Colour) -> bool {
+        match colour {
+            Colour::White => self.white_kingside,
+            Colour::Black => self.black_kingside,
+        }
+    }
+
+    /// Returns whether the given colour may still castle queenside.
+    #[inline]
+    pub fn queenside(&self, colour: Colour) -> bool {
+        match colour {
+            Colour::White => self.white_queenside,
+            Colour::Black => self.black_queenside,
+        }
+    }
+
+    /// Revokes any rights tied to a king or rook home square.
+    /// 
+    /// Called with both ends of every move, so that moving the king or a rook,
+    /// or capturing a rook at home, loses the matching right.
+    pub(crate) fn revoke_touching(&mut self, square: (usize, usize)) {
+        match square {
+            (0, 4) => {
+                self.white_kingside = false;
+                self.white_queenside = false;
+            }
+            (7, 4) => {
+                self.black_kingside = false;
+                self.black_queenside = false;
+            }
+            (0, 7) => self.white_kingside = false,
+            (0, 0) => self.white_queenside = false,
+            (7, 7) => self.black_kingside = false,
+            (7, 0) => self.black_queenside = false,
+            _ => {}
+        }
+    }
+}
+
 /// Denotes the state of the game.
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum GameState {
     /// The gameboard is in a neutral state.
     /// No one is in check or checkmate, and there are available moves.
@@ -281,6 +945,29 @@ pub enum GameState {
     /// The king of the returned colour is in checkmate.
     /// The game is over.
     Checkmate(Colour),
+    /// Fifty moves have passed by each side without a capture or pawn move.
+    /// The game is drawn.
+    FiftyMoveRule,
+    /// The same position has occurred three times.
+    /// The game is drawn.
+    ThreefoldRepetition,
+    /// Neither side has the material left to deliver checkmate.
+    /// The game is drawn.
+    InsufficientMaterial,
+}
+
+impl GameState {
+    /// Returns whether the game has ended in a draw.
+    pub fn is_draw(&self) -> bool {
+        use GameState::*;
+
+        matches!(self, Stalemate | FiftyMoveRule | ThreefoldRepetition | InsufficientMaterial)
+    }
+
+    /// Returns whether the game has ended, by checkmate or a draw.
+    pub fn is_over(&self) -> bool {
+        matches!(self, GameState::Checkmate(_)) || self.is_draw()
+    }
 }
 
 #[non_exhaustive]
@@ -331,6 +1018,420 @@ mod tests {
         assert_eq!(board.to_string(), test_board)
     }
 
+    fn legal_targets(board: &Board, row: usize, col: usize) -> Vec<(usize, usize)> {
+        let mut targets: Vec<_> = board.legal_moves()
+            .iter()
+            .filter(|m| m.origin() == (row, col))
+            .map(|m| m.target())
+            .collect();
+        targets.sort_unstable();
+
+        targets
+    }
+
+    #[test]
+    fn test_legal_moves_start() {
+        let board = Board::init();
+
+        assert_eq!(board.legal_moves().len(), 20);
+    }
+
+    #[test]
+    fn test_pinned_piece() {
+        use Colour::*;
+
+        let mut board = Board::empty();
+        board.place(PieceType::King(King::config(0, 4, White)));
+        board.place(PieceType::Rook(Rook::config(2, 4, White)));
+        board.place(PieceType::Knight(Knight::config(1, 3, White)));
+        board.place(PieceType::Queen(Queen::config(7, 4, Black)));
+        board.place(PieceType::Bishop(Bishop::config(4, 0, Black)));
+        board.place(PieceType::King(King::config(7, 7, Black)));
+
+        //the rook may only slide along the pin, the knight may not move at all
+        assert_eq!(legal_targets(&board, 2, 4), vec![(1, 4), (3, 4), (4, 4), (5, 4), (6, 4), (7, 4)]);
+        assert!(legal_targets(&board, 1, 3).is_empty());
+    }
+
+    #[test]
+    fn test_check_evasions() {
+        use Colour::*;
+
+        let mut board = Board::empty();
+        board.place(PieceType::King(King::config(0, 4, White)));
+        board.place(PieceType::Rook(Rook::config(0, 0, White)));
+        board.place(PieceType::Bishop(Bishop::config(4, 1, White)));
+        board.place(PieceType::Knight(Knight::config(3, 3, White)));
+        board.place(PieceType::Rook(Rook::config(5, 4, Black)));
+        board.place(PieceType::King(King::config(7, 7, Black)));
+        board.set_castling_rights(CastlingRights::all());
+
+        let mut all: Vec<_> = board.legal_moves()
+            .iter()
+            .map(|m| (m.origin(), m.target()))
+            .collect();
+        all.sort_unstable();
+
+        //step off the file, block with the bishop or knight, or take the rook
+        assert_eq!(all, vec![
+            ((0, 4), (0, 3)), ((0, 4), (0, 5)), ((0, 4), (1, 3)), ((0, 4), (1, 5)),
+            ((3, 3), (1, 4)), ((3, 3), (5, 4)), ((4, 1), (1, 4)),
+        ]);
+    }
+
+    #[test]
+    fn test_double_check() {
+        use Colour::*;
+
+        let mut board = Board::empty();
+        board.place(PieceType::King(King::config(0, 4, White)));
+        board.place(PieceType::Rook(Rook::config(3, 0, White)));
+        board.place(PieceType::Rook(Rook::config(7, 4, Black)));
+        board.place(PieceType::Bishop(Bishop::config(3, 1, Black)));
+        board.place(PieceType::King(King::config(7, 7, Black)));
+
+        let moves = board.legal_moves();
+
+        //taking the bishop still leaves the rook's check, so only the king may move
+        assert!(!moves.is_empty());
+        assert!(moves.iter().all(|m| m.origin() == (0, 4)));
+    }
+
+    #[test]
+    fn test_en_passant_discovered_check() {
+        use Colour::*;
+
+        let mut board = Board::empty();
+        board.place(PieceType::King(King::config(4, 0, White)));
+        board.place(PieceType::Pawn(Pawn::config(4, 1, White)));
+        board.place(PieceType::Pawn(Pawn::config(4, 2, Black)));
+        board.place(PieceType::King(King::config(7, 7, Black)));
+        board.set_en_passant(Some(Position {row: 5, col: 2}));
+
+        assert_eq!(legal_targets(&board, 4, 1), vec![(5, 1), (5, 2)]);
+
+        //taking en passant would clear the whole rank between king and rook
+        board.place(PieceType::Rook(Rook::config(4, 7, Black)));
+        assert_eq!(legal_targets(&board, 4, 1), vec![(5, 1)]);
+    }
+
+    #[test]
+    fn test_legal_castling() {
+        use Colour::*;
+
+        let mut board = Board::empty();
+        board.place(PieceType::King(King::config(7, 4, Black)));
+        board.place(PieceType::Rook(Rook::config(7, 7, Black)));
+        board.place(PieceType::Rook(Rook::config(7, 0, Black)));
+        board.place(PieceType::King(King::config(0, 4, White)));
+        board.place(PieceType::Bishop(Bishop::config(4, 0, White)));
+        board.set_castling_rights(CastlingRights::all());
+        board.flip_turn();
+
+        //the bishop covers d8, so only kingside castling remains
+        let targets = legal_targets(&board, 7, 4);
+        assert!(targets.contains(&(7, 6)));
+        assert!(!targets.contains(&(7, 2)));
+        assert!(!targets.contains(&(7, 3)));
+    }
+
+    #[test]
+    fn test_move_piece_rejects_illegal() {
+        let mut board = Board::init();
+
+        assert_eq!(
+            board.move_piece(Move::construct((3, 4), (0, 4))),
+            Err(GameError::InvalidMove)
+        );
+        assert_eq!(
+            board.move_piece(Move::construct((4, 4), (6, 4))),
+            Err(GameError::WrongTurn)
+        );
+        assert_eq!(
+            board.move_piece(Move::construct((3, 4), (3, 3))),
+            Err(GameError::EmptySpace)
+        );
+    }
+
+    #[test]
+    fn test_move_piece_and_unmake() {
+        let mut board = Board::init();
+
+        board.move_piece(Move::construct((3, 4), (1, 4))).unwrap();
+        board.move_piece(Move::construct((4, 3), (6, 3))).unwrap();
+        board.move_piece(Move::construct((4, 3), (3, 4))).unwrap();
+
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkbnr/ppp1pppp/8/3P4/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2"
+        );
+        assert_eq!(board.history().len(), 3);
+        assert_eq!(board.white().len(), 1);
+
+        assert_eq!(board.unmake_move(), Some(Move::construct((4, 3), (3, 4))));
+        assert!(board.white().is_empty());
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2"
+        );
+
+        board.unmake_move();
+        board.unmake_move();
+        assert_eq!(board.to_fen(), crate::fen::STARTING_FEN);
+        assert_eq!(board.unmake_move(), None);
+    }
+
+    /// Checks the bitboards against the grid they mirror.
+    fn assert_bitboards_match(board: &Board) {
+        let mut kinds = [0; 6];
+        let mut colours = [0; 2];
+
+        for (row, pieces) in board.grid().iter().enumerate() {
+            for (col, piece) in pieces.iter().enumerate() {
+                if let (Some(kind), Some(colour)) = (piece.kind(), piece.colour()) {
+                    kinds[kind as usize] |= 1 << bitboard::square(row, col);
+                    colours[colour as usize] |= 1 << bitboard::square(row, col);
+                }
+            }
+        }
+
+        assert_eq!(board.kinds, kinds, "{}", board.to_fen());
+        assert_eq!(board.colours, colours, "{}", board.to_fen());
+    }
+
+    #[test]
+    fn test_unmake_restores_exactly() {
+        //castling, en passant, promotions and promotion captures all appear here
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkbnr/pp1p1ppp/8/2pPp3/8/8/PPP1PPPP/RNBQKBNR w KQkq e6 0 3",
+        ];
+
+        for fen in fens.iter() {
+            let mut board = Board::from_fen(fen).unwrap();
+
+            for mov in board.legal_moves() {
+                board.play(mov);
+
+                for reply in board.legal_moves() {
+                    let before = board.to_fen();
+                    board.play(reply);
+                    assert_bitboards_match(&board);
+                    assert_eq!(board.unmake_move(), Some(reply));
+                    assert_eq!(board.to_fen(), before);
+                }
+
+                assert_eq!(board.unmake_move(), Some(mov));
+                assert_eq!(board.to_fen(), *fen);
+                assert!(board.white().is_empty() && board.black().is_empty());
+                assert!(board.history().is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_gamestate_check_and_mate() {
+        let mut board = Board::init();
+        assert_eq!(board.eval_gamestate(), GameState::None);
+
+        //fool's mate
+        board.move_piece(Move::construct((2, 5), (1, 5))).unwrap();
+        board.move_piece(Move::construct((4, 4), (6, 4))).unwrap();
+        board.move_piece(Move::construct((3, 6), (1, 6))).unwrap();
+        board.move_piece(Move::construct((3, 7), (7, 3))).unwrap();
+
+        let state = board.eval_gamestate();
+        assert_eq!(state, GameState::Checkmate(Colour::White));
+        assert!(state.is_over() && !state.is_draw());
+        assert!(board.is_in_check());
+
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/3q4/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.eval_gamestate(), GameState::Check(Colour::White));
+    }
+
+    #[test]
+    fn test_gamestate_stalemate() {
+        let mut board = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        let state = board.eval_gamestate();
+
+        assert_eq!(state, GameState::Stalemate);
+        assert!(state.is_draw());
+        assert!(!board.is_in_check());
+    }
+
+    #[test]
+    fn test_gamestate_fifty_moves() {
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/4P3/4K2R w - - 99 80").unwrap();
+        assert_eq!(board.eval_gamestate(), GameState::None);
+
+        board.move_piece(Move::construct((0, 6), (0, 7))).unwrap();
+        assert_eq!(board.eval_gamestate(), GameState::FiftyMoveRule);
+
+        //checkmate on the hundredth ply still wins
+        let mut board = Board::from_fen("4k3/8/4K3/8/8/8/8/7R w - - 99 80").unwrap();
+        board.move_piece(Move::construct((7, 7), (0, 7))).unwrap();
+        assert_eq!(board.eval_gamestate(), GameState::Checkmate(Colour::Black));
+    }
+
+    #[test]
+    fn test_gamestate_threefold() {
+        let mut board = Board::init();
+        let shuffle = [
+            Move::construct((2, 5), (0, 6)),
+            Move::construct((5, 5), (7, 6)),
+            Move::construct((0, 6), (2, 5)),
+            Move::construct((7, 6), (5, 5)),
+        ];
+
+        for mov in shuffle.iter() {
+            board.move_piece(*mov).unwrap();
+            assert_eq!(board.eval_gamestate(), GameState::None);
+        }
+        for mov in shuffle[..3].iter() {
+            board.move_piece(*mov).unwrap();
+            assert_eq!(board.eval_gamestate(), GameState::None);
+        }
+
+        board.move_piece(shuffle[3]).unwrap();
+        assert_eq!(board.eval_gamestate(), GameState::ThreefoldRepetition);
+    }
+
+    #[test]
+    fn test_gamestate_insufficient_material() {
+        let drawn = [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2N1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2B1K3 b - - 0 1",
+            "2b1k3/8/8/8/8/8/8/3BK3 w - - 0 1",
+        ];
+        let playable = [
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1",
+            "3bk3/8/8/8/8/8/8/3BK3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2BNK3 w - - 0 1",
+        ];
+
+        for fen in drawn.iter() {
+            let mut board = Board::from_fen(fen).unwrap();
+            assert_eq!(board.eval_gamestate(), GameState::InsufficientMaterial, "{}", fen);
+        }
+        for fen in playable.iter() {
+            let mut board = Board::from_fen(fen).unwrap();
+            assert_eq!(board.eval_gamestate(), GameState::None, "{}", fen);
+        }
+    }
+
+    fn squares(bb: Bitboard) -> Vec<String> {
+        bitboard::squares(bb).map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_attackers_to() {
+        let board = Board::from_fen("4k3/8/3p4/2N5/4R3/8/1B6/4K3 w - - 0 1").unwrap();
+        let e4 = Position::from_square("e4").unwrap();
+        let d4 = Position::from_square("d4").unwrap();
+
+        assert_eq!(squares(board.attackers_to(d4)), vec!["b2", "e4"]);
+        assert!(board.is_square_attacked(e4, Colour::White));
+        assert!(!board.is_square_attacked(e4, Colour::Black));
+        //pieces of both colours bear on e5
+        let e5 = Position::from_square("e5").unwrap();
+        assert_eq!(squares(board.attackers_to(e5)), vec!["b2", "e4", "d6"]);
+    }
+
+    #[test]
+    fn test_checkers() {
+        assert_eq!(Board::init().checkers(), 0);
+
+        let board = Board::from_fen("4k3/8/3N4/8/8/8/8/4R1K1 b - - 0 1").unwrap();
+        assert_eq!(squares(board.checkers()), vec!["e1", "d6"]);
+    }
+
+    #[test]
+    fn test_pinned_pieces() {
+        //the knight is pinned by the bishop, the rook only along its own file,
+        //and the bishop on f1 is shielded by the knight beside it
+        let board = Board::from_fen("4r2k/8/8/b7/8/2N5/4R3/4KBNq w - - 0 1").unwrap();
+        assert_eq!(squares(board.pinned_pieces(Colour::White)), vec!["e2", "c3"]);
+        assert_eq!(board.pinned_pieces(Colour::Black), 0);
+
+        let board = Board::from_fen("4k3/8/8/1b6/8/3N4/4P3/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.pinned_pieces(Colour::White), 0);
+    }
+
+    #[test]
+    fn test_bitboards() {
+        let board = Board::init();
+        assert_bitboards_match(&board);
+
+        assert_eq!(board.occupied(), 0xffff_0000_0000_ffff);
+        assert_eq!(board.pieces_of_colour(Colour::White), 0xffff);
+        assert_eq!(board.pieces_of_kind(PieceKind::Pawn), 0x00ff_0000_0000_ff00);
+        assert_eq!(board.pieces(PieceKind::Knight, Colour::Black), 1 << 57 | 1 << 62);
+        assert!(Board::empty().is_empty());
+    }
+
+    #[test]
+    fn test_previous_move_after_null() {
+        let mut board = Board::init();
+        assert_eq!(board.previous_move(), None);
+
+        let e4 = board.parse_uci("e2e4").unwrap();
+        board.play(e4);
+        assert_eq!(board.previous_move(), Some(e4));
+
+        //a pass hides the move before it, until a real move follows or it is taken back
+        let undo = board.play_null();
+        assert_eq!(board.previous_move(), None);
+
+        let nf3 = board.parse_uci("g1f3").unwrap();
+        board.play(nf3);
+        assert_eq!(board.previous_move(), Some(nf3));
+        board.unmake_move();
+        assert_eq!(board.previous_move(), None);
+
+        board.unmake_null(undo);
+        assert_eq!(board.previous_move(), Some(e4));
+    }
+
+    #[test]
+    fn test_log_move() {
+        let mut board = Board::init();
+        let knight = board[0][6];
+
+        //a move made by hand can be taken back once logged
+        board.log_move(knight, Move::construct((2, 5), (0, 6)));
+        board.remove(0, 6);
+        board.place(knight.moved_to(2, 5));
+        board.flip_turn();
+
+        assert_eq!(board.history().len(), 1);
+        assert_eq!(board.history()[0].piece, knight);
+        assert_eq!(board.history()[0].hash, Board::init().hash());
+
+        assert_eq!(board.unmake_move(), Some(Move::construct((2, 5), (0, 6))));
+        assert_eq!(board.to_fen(), crate::fen::STARTING_FEN);
+        assert_eq!(board.hash(), Board::init().hash());
+    }
+
+    #[test]
+    fn test_edit_squares() {
+        let mut board = Board::init();
+
+        assert_eq!(board.remove(1, 4), PieceType::Pawn(Pawn::config(1, 4, Colour::White)));
+        board.place(PieceType::Pawn(Pawn::config(3, 4, Colour::White)));
+        assert_bitboards_match(&board);
+
+        //writes through indexing catch up once resynced
+        board[1][3] = PieceType::Empty;
+        board[3][3] = PieceType::Pawn(Pawn::config(3, 3, Colour::White));
+        board.resync();
+        assert_bitboards_match(&board);
+        assert_eq!(board.hash(), board.compute_hash());
+        assert_eq!(board.pieces(PieceKind::Pawn, Colour::White), 0x0000_0000_1800_e700);
+    }
+
     #[test]
     fn test_find_king() {
         let board = Board::init();
@@ -0,0 +1,328 @@
+//! Static evaluation of a position.
+//!
+//! A position is scored from material, using each piece's `POINTS`,
+//! and piece-square tables that reward good squares. The tables come in
+//! middlegame and endgame flavours, blended by how much material is left.
+
+use std::fmt;
+
+use crate::bitboard;
+use crate::board::Board;
+use crate::pieces::*;
+
+/// Game phase contributed by each kind of piece still on the board.
+/// The starting position adds up to `MAX_PHASE`.
+const fn phase_weight(kind: PieceKind) -> i32 {
+    match kind {
+        PieceKind::Knight | PieceKind::Bishop => 1,
+        PieceKind::Rook => 2,
+        PieceKind::Queen => 4,
+        PieceKind::Pawn | PieceKind::King => 0,
+    }
+}
+
+/// The phase of the starting position; 0 is a bare endgame.
+pub const MAX_PHASE: i32 = 24;
+
+/// Returns the material value of a piece in centipawns.
+/// The king is priceless, so it is not counted.
+pub fn piece_value(kind: PieceKind) -> i32 {
+    let points = match kind {
+        PieceKind::Pawn => pawn::POINTS,
+        PieceKind::Knight => knight::POINTS,
+        PieceKind::Bishop => bishop::POINTS,
+        PieceKind::Rook => rook::POINTS,
+        PieceKind::Queen => queen::POINTS,
+        PieceKind::King => 0,
+    };
+
+    points as i32 * 100
+}
+
+/// Every term of an evaluation, for debugging.
+///
+/// Terms are from white's point of view, except `total`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EvalBreakdown {
+    /// Material balance.
+    pub material: i32,
+    /// Piece-square balance using the middlegame tables.
+    pub psqt_midgame: i32,
+    /// Piece-square balance using the endgame tables.
+    pub psqt_endgame: i32,
+    /// How much material is left, from `MAX_PHASE` down to 0.
+    pub phase: i32,
+    /// The two piece-square balances blended by phase.
+    pub psqt: i32,
+    /// The final score from the side to move's point of view.
+    pub total: i32,
+}
+
+impl fmt::Display for EvalBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "material      {:>6}", self.material)?;
+        writeln!(f, "psqt (mg)     {:>6}", self.psqt_midgame)?;
+        writeln!(f, "psqt (eg)     {:>6}", self.psqt_endgame)?;
+        writeln!(f, "phase         {:>6}", self.phase)?;
+        writeln!(f, "psqt          {:>6}", self.psqt)?;
+        write!(f, "total (stm)   {:>6}", self.total)
+    }
+}
+
+/// Scores the position in centipawns from the side to move's point of view.
+pub fn evaluate(board: &Board) -> i32 {
+    breakdown(board).total
+}
+
+/// Scores the position, reporting each term separately.
+pub fn breakdown(board: &Board) -> EvalBreakdown {
+    let mut material = 0;
+    let mut midgame = 0;
+    let mut endgame = 0;
+    let mut phase = 0;
+
+    for pos in bitboard::squares(board.occupied()) {
+        let piece = board[pos.row][pos.col];
+        let (kind, colour) = match (piece.kind(), piece.colour()) {
+            (Some(kind), Some(colour)) => (kind, colour),
+            _ => continue,
+        };
+
+        //the tables are written from white's side, eighth rank first
+        let square = match colour {
+            Colour::White => (7 - pos.row) * 8 + pos.col,
+            Colour::Black => pos.row * 8 + pos.col,
+        };
+        let sign = if colour == Colour::White { 1 } else { -1 };
+
+        material += sign * piece_value(kind);
+        midgame += sign * midgame_table(kind)[square];
+        endgame += sign * endgame_table(kind)[square];
+        phase += phase_weight(kind);
+    }
+
+    //promotions can push the phase past the start
+    let phase = phase.min(MAX_PHASE);
+    let psqt = (midgame * phase + endgame * (MAX_PHASE - phase)) / MAX_PHASE;
+
+    let white = material + psqt;
+    let total = match board.turn() {
+        Colour::White => white,
+        Colour::Black => -white,
+    };
+
+    EvalBreakdown {
+        material,
+        psqt_midgame: midgame,
+        psqt_endgame: endgame,
+        phase,
+        psqt,
+        total,
+    }
+}
+
+fn midgame_table(kind: PieceKind) -> &'static [i32; 64] {
+    match kind {
+        PieceKind::Pawn => &PAWN_MG,
+        PieceKind::Knight => &KNIGHT,
+        PieceKind::Bishop => &BISHOP,
+        PieceKind::Rook => &ROOK,
+        PieceKind::Queen => &QUEEN,
+        PieceKind::King => &KING_MG,
+    }
+}
+
+fn endgame_table(kind: PieceKind) -> &'static [i32; 64] {
+    match kind {
+        PieceKind::Pawn => &PAWN_EG,
+        PieceKind::Knight => &KNIGHT,
+        PieceKind::Bishop => &BISHOP,
+        PieceKind::Rook => &ROOK_EG,
+        PieceKind::Queen => &QUEEN,
+        PieceKind::King => &KING_EG,
+    }
+}
+
+#[rustfmt::skip]
+const PAWN_MG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     10,  10,  20,  30,  30,  20,  10,  10,
+      5,   5,  10,  25,  25,  10,   5,   5,
+      0,   0,   0,  20,  20,   0,   0,   0,
+      5,  -5, -10,   0,   0, -10,  -5,   5,
+      5,  10,  10, -20, -20,  10,  10,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const PAWN_EG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     80,  80,  80,  80,  80,  80,  80,  80,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     30,  30,  30,  30,  30,  30,  30,  30,
+     15,  15,  15,  15,  15,  15,  15,  15,
+      5,   5,   5,   5,   5,   5,   5,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const KNIGHT: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const BISHOP: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const ROOK: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+      5,  10,  10,  10,  10,  10,  10,   5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+      0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const ROOK_EG: [i32; 64] = [
+     10,  10,  10,  10,  10,  10,  10,  10,
+     15,  15,  15,  15,  15,  15,  15,  15,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const QUEEN: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const KING_MG: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+#[rustfmt::skip]
+const KING_EG: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flips the board vertically and swaps the colours.
+    fn mirror(fen: &str) -> String {
+        let fields: Vec<&str> = fen.split(' ').collect();
+        let ranks: Vec<String> = fields[0].split('/')
+            .rev()
+            .map(|rank| rank.chars().map(|c| {
+                if c.is_ascii_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() }
+            }).collect())
+            .collect();
+        let turn = if fields[1] == "w" { "b" } else { "w" };
+
+        format!("{} {} - - 0 1", ranks.join("/"), turn)
+    }
+
+    #[test]
+    fn test_start_position_is_level() {
+        let eval = breakdown(&Board::init());
+
+        assert_eq!(eval.material, 0);
+        assert_eq!(eval.psqt, 0);
+        assert_eq!(eval.phase, MAX_PHASE);
+        assert_eq!(eval.total, 0);
+    }
+
+    #[test]
+    fn test_eval_is_symmetric() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w - - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R b - - 0 1",
+        ];
+
+        for fen in fens.iter() {
+            let board = Board::from_fen(fen).unwrap();
+            let mirrored = Board::from_fen(&mirror(fen)).unwrap();
+
+            assert_eq!(evaluate(&board), evaluate(&mirrored), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_eval_side_to_move() {
+        let white = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let black = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+
+        assert!(evaluate(&white) > 800);
+        assert_eq!(evaluate(&white), -evaluate(&black));
+        assert_eq!(breakdown(&white).material, 900);
+    }
+
+    #[test]
+    fn test_eval_prefers_good_squares() {
+        let centre = Board::from_fen("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+        let corner = Board::from_fen("4k3/8/8/8/8/8/8/N3K3 w - - 0 1").unwrap();
+
+        assert!(evaluate(&centre) > evaluate(&corner));
+    }
+
+    #[test]
+    fn test_eval_tapers_king_safety() {
+        //with queens on, the king belongs at home; without, in the centre
+        let home = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1";
+        let centre = "rnbqkbnr/pppppppp/8/8/4K3/8/PPPPPPPP/RNBQ1BNR w - - 0 1";
+        assert!(evaluate(&Board::from_fen(home).unwrap()) > evaluate(&Board::from_fen(centre).unwrap()));
+
+        let home = "4k3/p7/8/8/8/8/P7/6K1 w - - 0 1";
+        let centre = "4k3/p7/8/8/4K3/8/P7/8 w - - 0 1";
+        assert!(evaluate(&Board::from_fen(home).unwrap()) < evaluate(&Board::from_fen(centre).unwrap()));
+        assert_eq!(breakdown(&Board::from_fen(home).unwrap()).phase, 0);
+    }
+}
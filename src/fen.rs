@@ -0,0 +1,341 @@
+//! Reading and writing positions in Forsyth-Edwards Notation.
+
+use std::fmt;
+use std::error::Error;
+
+use crate::board::{Board, CastlingRights};
+use crate::pieces::*;
+
+/// The FEN of the standard starting position.
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+impl Board {
+    /// Constructs a Board from a FEN string.
+    /// 
+    /// The halfmove clock and fullmove number may be left off,
+    /// in which case they default to 0 and 1.
+    pub fn from_fen(fen: &str) -> Result<Board, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+
+        if fields.len() != 4 && fields.len() != 6 {
+            return Err(FenError::WrongFieldCount(fields.len()))
+        }
+
+        let mut board = Board::empty();
+
+        //ranks are listed from the eighth down to the first
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::WrongRankCount(ranks.len()))
+        }
+
+        for (i, rank) in ranks.iter().enumerate() {
+            let row = 7 - i;
+            let mut col = 0;
+
+            for c in rank.chars() {
+                if let Some(skip) = c.to_digit(10) {
+                    if skip == 0 || skip > 8 {
+                        return Err(FenError::InvalidRank(row + 1))
+                    }
+                    col += skip as usize;
+                } else {
+                    if col >= 8 {
+                        return Err(FenError::InvalidRank(row + 1))
+                    }
+                    let piece = PieceType::from_char(c, row, col)
+                        .ok_or(FenError::InvalidPiece(c))?;
+                    if (c == 'P' || c == 'p') && (row == 0 || row == 7) {
+                        return Err(FenError::PawnOnBackRank(row + 1))
+                    }
+                    board.place(piece);
+                    col += 1;
+                }
+            }
+
+            if col != 8 {
+                return Err(FenError::InvalidRank(row + 1))
+            }
+        }
+
+        for &colour in [Colour::White, Colour::Black].iter() {
+            let kings = board.pieces(PieceKind::King, colour).count_ones() as usize;
+
+            if kings != 1 {
+                return Err(FenError::KingCount(colour, kings))
+            }
+        }
+
+        match fields[1] {
+            "w" => {}
+            "b" => board.flip_turn(),
+            other => return Err(FenError::InvalidTurn(other.to_string())),
+        }
+
+        board.set_castling_rights(parse_castling(fields[2])?);
+
+        board.set_en_passant(match fields[3] {
+            "-" => None,
+            square => {
+                let pos = Position::from_square(square)
+                    .ok_or_else(|| FenError::InvalidEnPassant(square.to_string()))?;
+
+                //the skipped square lies behind the pawn that just moved,
+                //on the sixth rank if white is to move and the third if black is,
+                //and both it and the square the pawn came from must be empty
+                let (row, pawn_row, from_row, mover) = match board.turn() {
+                    Colour::White => (5, 4, 6, Colour::Black),
+                    Colour::Black => (2, 3, 1, Colour::White),
+                };
+                let pawn = board[pawn_row][pos.col];
+                let valid = pos.row == row
+                    && pawn.is_pawn() && pawn.colour() == Some(mover)
+                    && board[row][pos.col].is_empty()
+                    && board[from_row][pos.col].is_empty();
+
+                if !valid {
+                    return Err(FenError::InvalidEnPassant(square.to_string()))
+                }
+                Some(pos)
+            }
+        });
+
+        if fields.len() == 6 {
+            let halfmove = fields[4].parse()
+                .map_err(|_| FenError::InvalidClock(fields[4].to_string()))?;
+            let fullmove = fields[5].parse()
+                .map_err(|_| FenError::InvalidClock(fields[5].to_string()))?;
+
+            if fullmove == 0 {
+                return Err(FenError::InvalidClock(fields[5].to_string()))
+            }
+            board.set_clocks(halfmove, fullmove);
+        }
+
+        Ok(board)
+    }
+
+    /// Serializes the Board into a FEN string.
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+
+        for row in (0..8).rev() {
+            let mut empty = 0;
+
+            for col in 0..8 {
+                let piece = self[row][col];
+
+                if piece.is_empty() {
+                    empty += 1;
+                    continue
+                }
+                if empty > 0 {
+                    fen.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                fen.push(piece.as_char());
+            }
+
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if row > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen.push_str(match self.turn() {
+            Colour::White => " w ",
+            Colour::Black => " b ",
+        });
+
+        let rights = self.castling_rights();
+        let mut castling = String::new();
+        if rights.white_kingside { castling.push('K') }
+        if rights.white_queenside { castling.push('Q') }
+        if rights.black_kingside { castling.push('k') }
+        if rights.black_queenside { castling.push('q') }
+        if castling.is_empty() { castling.push('-') }
+        fen.push_str(&castling);
+
+        match self.en_passant() {
+            Some(pos) => fen.push_str(&format!(" {}", pos)),
+            None => fen.push_str(" -"),
+        }
+
+        fen.push_str(&format!(" {} {}", self.halfmove_clock(), self.fullmove_number()));
+
+        fen
+    }
+}
+
+fn parse_castling(field: &str) -> Result<CastlingRights, FenError> {
+    let mut rights = CastlingRights::none();
+
+    if field == "-" {
+        return Ok(rights)
+    }
+
+    for c in field.chars() {
+        let right = match c {
+            'K' => &mut rights.white_kingside,
+            'Q' => &mut rights.white_queenside,
+            'k' => &mut rights.black_kingside,
+            'q' => &mut rights.black_queenside,
+            _ => return Err(FenError::InvalidCastling(field.to_string())),
+        };
+
+        if *right {
+            return Err(FenError::InvalidCastling(field.to_string()))
+        }
+        *right = true;
+    }
+
+    Ok(rights)
+}
+
+/// Describes why a FEN string could not be parsed.
+#[non_exhaustive]
+#[derive(Clone, PartialEq, Debug)]
+pub enum FenError {
+    /// The FEN did not have 4 or 6 space-separated fields.
+    WrongFieldCount(usize),
+    /// The piece placement did not have 8 ranks.
+    WrongRankCount(usize),
+    /// The given rank (1-8) did not describe exactly 8 squares.
+    InvalidRank(usize),
+    /// The character does not denote any piece.
+    InvalidPiece(char),
+    /// A pawn stood on the given rank, the first or eighth.
+    PawnOnBackRank(usize),
+    /// The given colour did not have exactly one king.
+    KingCount(Colour, usize),
+    /// The side to move was not `w` or `b`.
+    InvalidTurn(String),
+    /// The castling field was not `-` or a combination of `KQkq`.
+    InvalidCastling(String),
+    /// The en passant field was not `-` or an empty square just behind
+    /// a pawn of the side not to move that could have made a double push:
+    /// on the sixth rank if white is to move, the third if black is.
+    InvalidEnPassant(String),
+    /// The halfmove clock or fullmove number was not a valid number.
+    InvalidClock(String),
+}
+
+impl Error for FenError {}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::WrongFieldCount(n) => {
+                write!(f, "Expected 4 or 6 fields, found {}", n)
+            }
+            Self::WrongRankCount(n) => {
+                write!(f, "Expected 8 ranks, found {}", n)
+            }
+            Self::InvalidRank(rank) => {
+                write!(f, "Rank {} does not describe exactly 8 squares", rank)
+            }
+            Self::InvalidPiece(c) => {
+                write!(f, "Invalid piece '{}'", c)
+            }
+            Self::PawnOnBackRank(rank) => {
+                write!(f, "Pawn on rank {}", rank)
+            }
+            Self::KingCount(colour, n) => {
+                write!(f, "Expected one {:?} king, found {}", colour, n)
+            }
+            Self::InvalidTurn(s) => {
+                write!(f, "Invalid side to move '{}'", s)
+            }
+            Self::InvalidCastling(s) => {
+                write!(f, "Invalid castling rights '{}'", s)
+            }
+            Self::InvalidEnPassant(s) => {
+                write!(f, "Invalid en passant square '{}'", s)
+            }
+            Self::InvalidClock(s) => {
+                write!(f, "Invalid move counter '{}'", s)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_starting_fen() {
+        let board = Board::from_fen(STARTING_FEN).unwrap();
+
+        assert_eq!(board.to_string(), Board::init().to_string());
+        assert_eq!(Board::init().to_fen(), STARTING_FEN);
+        assert_eq!(board.castling_rights(), CastlingRights::all());
+    }
+
+    #[test]
+    fn test_fen_round_trip() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2",
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 b Kq - 13 27",
+        ];
+
+        for fen in fens.iter() {
+            assert_eq!(Board::from_fen(fen).unwrap().to_fen(), *fen);
+        }
+    }
+
+    #[test]
+    fn test_fen_fields() {
+        let board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 3 40").unwrap();
+
+        assert_eq!(board.turn(), Colour::White);
+        assert_eq!(board.castling_rights(), CastlingRights::none());
+        assert_eq!(board.en_passant(), Some(Position {row: 5, col: 3}));
+        assert_eq!(board.halfmove_clock(), 3);
+        assert_eq!(board.fullmove_number(), 40);
+        assert!(board[4][3].is_pawn() && board[4][3].colour() == Some(Colour::Black));
+    }
+
+    #[test]
+    fn test_fen_without_clocks() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 b - -").unwrap();
+
+        assert_eq!(board.turn(), Colour::Black);
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+    }
+
+    #[test]
+    fn test_fen_errors() {
+        use FenError::*;
+
+        let cases = [
+            ("", WrongFieldCount(0)),
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0", WrongFieldCount(5)),
+            ("4k3/8/8/8/8/8/4K3 w - - 0 1", WrongRankCount(7)),
+            ("4k3/8/8/8/8/8/8/4K4 w - - 0 1", InvalidRank(1)),
+            ("4k3/8/8/8/8/8/7/4K3 w - - 0 1", InvalidRank(2)),
+            ("4k3/8/8/8/8/8/8/4X3 w - - 0 1", InvalidPiece('X')),
+            ("8/8/8/8/8/8/8/4K3 w - - 0 1", KingCount(Colour::Black, 0)),
+            ("4k3/8/8/8/8/8/8/4K3 x - - 0 1", InvalidTurn("x".to_string())),
+            ("4k3/8/8/8/8/8/8/4K3 w KK - 0 1", InvalidCastling("KK".to_string())),
+            ("4k3/8/8/8/8/8/8/4K3 w - e4 0 1", InvalidEnPassant("e4".to_string())),
+            ("4k3/8/8/8/4P3/8/8/4K3 w - e3 0 1", InvalidEnPassant("e3".to_string())),
+            ("4k3/8/8/4p3/8/8/8/4K3 b - e6 0 1", InvalidEnPassant("e6".to_string())),
+            ("4k3/8/8/3P4/8/8/8/4K3 w - e6 0 1", InvalidEnPassant("e6".to_string())),
+            ("4k3/8/4n3/4p3/8/8/8/4K3 w - e6 0 1", InvalidEnPassant("e6".to_string())),
+            ("4k3/4r3/8/4p3/8/8/8/4K3 w - e6 0 1", InvalidEnPassant("e6".to_string())),
+            ("4k3/8/8/8/8/8/8/P3K3 w - - 0 1", PawnOnBackRank(1)),
+            ("3pk3/8/8/8/8/8/8/4K3 w - - 0 1", PawnOnBackRank(8)),
+            ("4k3/8/8/8/8/8/8/4K3 w - - a 1", InvalidClock("a".to_string())),
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 0", InvalidClock("0".to_string())),
+        ];
+
+        for (fen, err) in cases.iter() {
+            assert_eq!(Board::from_fen(fen).err().as_ref(), Some(err), "{}", fen);
+        }
+    }
+}
@@ -1,6 +1,20 @@
-pub mod pieces; 
+pub mod pieces;
+pub mod bitboard;
+pub mod attacks;
 pub mod board;
 pub mod moves;
+pub mod fen;
+pub mod perft;
+pub mod san;
+pub mod eval;
+pub mod see;
+pub mod zobrist;
+pub mod tt;
+pub mod ordering;
+pub mod time;
+pub mod search;
+pub mod uci;
+pub mod xboard;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,24 @@
+use std::io::{self, BufRead, Cursor, Read};
+
+/// Picks the protocol from the first command the interface sends:
+/// XBoard interfaces open with `xboard`, everything else is treated as UCI.
+fn main() {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+
+    let mut first = String::new();
+    if input.read_line(&mut first).is_err() {
+        return
+    }
+
+    //build the attack tables before the first search needs them
+    rustgambit::attacks::init();
+
+    let replay = Cursor::new(first.clone());
+
+    if first.trim() == "xboard" {
+        rustgambit::xboard::run(replay.chain(input), io::stdout());
+    } else {
+        rustgambit::uci::run(replay.chain(input), io::stdout());
+    }
+}
@@ -1,20 +1,34 @@
-use core::ops::{Deref, DerefMut};
+use core::ops::Deref;
 use core::convert::AsRef;
 use std::vec::IntoIter;
+use std::fmt;
+use std::error::Error;
+use std::str::FromStr;
+
+use crate::board::Board;
+use crate::pieces::{PieceKind, Position};
 
 /// Allows a generic type to be marked as a Move.
 pub trait MoveMarker {
     fn target(&self) -> (usize, usize);
     fn origin(&self) -> (usize, usize);
+
+    /// The kind of piece a pawn promotes to, if the move is a promotion.
+    fn promotion(&self) -> Option<PieceKind> {
+        None
+    }
 }
 
 /// Contains the set of Moves that a particular piece can take.
 /// 
-/// Returned by `Piece::evaluate_moves`.
+/// Returned by `Piece::evaluate_moves`. Each move carries a score,
+/// zero unless set, that move ordering uses to try the best moves first.
 #[derive(Clone)]
 pub struct MoveSet {
     is_checking: bool,
-    moves: Vec<Move>
+    moves: Vec<Move>,
+    // kept in step with moves, one score per move
+    scores: Vec<i32>,
 }
 
 impl MoveSet {
@@ -24,11 +38,18 @@ impl MoveSet {
     pub fn from<T: IntoIterator>(iter: T) -> Self
     where T::Item: MoveMarker {
 
+        let moves: Vec<Move> = iter.into_iter().map(|item| {
+            Move {
+                target: item.target(),
+                origin: item.origin(),
+                promotion: item.promotion(),
+            }
+        }).collect();
+
         MoveSet {
             is_checking: false,
-            moves: iter.into_iter().map(|item| {
-                Move::construct(item.target(), item.origin())
-            }).collect()
+            scores: vec![0; moves.len()],
+            moves,
         }
     }
 
@@ -36,9 +57,55 @@ impl MoveSet {
         MoveSet {
             is_checking: true,
             moves: Vec::new(),
+            scores: Vec::new(),
         }
     }
 
+    /// Returns the score of the move at the given index.
+    #[inline]
+    pub fn score(&self, index: usize) -> i32 {
+        self.scores[index]
+    }
+
+    /// Sets the score of the move at the given index.
+    #[inline]
+    pub fn set_score(&mut self, index: usize, score: i32) {
+        self.scores[index] = score;
+    }
+
+    /// Returns the scores of the moves, in the same order as the moves.
+    #[inline]
+    pub fn scores(&self) -> &[i32] {
+        &self.scores
+    }
+
+    /// Swaps two moves along with their scores.
+    /// 
+    /// The moves can only be reordered through here, so that
+    /// each one keeps its score.
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.moves.swap(a, b);
+        self.scores.swap(a, b);
+    }
+
+    /// Finds the highest scoring move from `start` onwards, swaps it into
+    /// place at `start` and returns it with its score.
+    /// 
+    /// Calling this with `start` counting up from 0 yields the moves
+    /// best first, without sorting moves that are never reached.
+    pub fn pick_best(&mut self, start: usize) -> Option<(Move, i32)> {
+        let best = (start..self.len()).max_by_key(|&i| (self.scores[i], std::cmp::Reverse(i)))?;
+        self.swap(start, best);
+
+        Some((self.moves[start], self.scores[start]))
+    }
+
+    /// Marks the MoveSet as checking the enemy king.
+    #[inline]
+    pub(crate) fn mark_checking(&mut self) {
+        self.is_checking = true;
+    }
+
     /// Returns the number of Moves in the MoveSet
     #[inline]
     pub fn len(&self) -> usize {
@@ -63,7 +130,7 @@ impl MoveSet {
     pub fn iter(&self) -> MoveSetIter<'_> {
         MoveSetIter {
             idx: 0,
-            inner: &self,
+            inner: self,
         }
     }
 }
@@ -88,14 +155,6 @@ impl Deref for MoveSet {
     }
 }
 
-impl DerefMut for MoveSet {
-
-    #[inline]
-    fn deref_mut(&mut self) -> &mut [Move] {
-        &mut self.moves
-    }
-}
-
 impl AsRef<[Move]> for MoveSet {
 
     #[inline]
@@ -104,14 +163,6 @@ impl AsRef<[Move]> for MoveSet {
     }
 }
 
-impl<'a> AsMut<[Move]> for MoveSet {
-
-    #[inline]
-    fn as_mut(&mut self) -> &mut [Move] {
-        &mut self.moves
-    }
-}
-
 #[derive(Debug, Clone, Copy)]
 pub struct MoveSetIter<'a> {
     idx: usize,
@@ -122,12 +173,10 @@ impl<'a> Iterator for MoveSetIter<'a> {
     type Item = &'a Move;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.idx + 1 == self.inner.len() {
-            return None
-        } else {
-            self.idx += 1;
-            return Some(&self.inner[self.idx])
-        }
+        let item = self.inner.get(self.idx)?;
+        self.idx += 1;
+
+        Some(item)
     }
 }
 
@@ -144,10 +193,11 @@ impl Iterator for MoveSetIntoIter {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Move {
     target: (usize, usize),
     origin: (usize, usize),
+    promotion: Option<PieceKind>,
 }
 
 impl Move {
@@ -155,6 +205,16 @@ impl Move {
         Move {
             target: t,
             origin: o,
+            promotion: None,
+        }
+    }
+
+    /// Constructs a pawn move to the last rank that promotes to `kind`.
+    pub(crate) fn promoting(t: (usize, usize), o: (usize, usize), kind: PieceKind) -> Self {
+        Move {
+            target: t,
+            origin: o,
+            promotion: Some(kind),
         }
     }
 }
@@ -167,6 +227,167 @@ impl MoveMarker for Move {
     fn origin(&self) -> (usize, usize) {
         self.origin
     }
+
+    fn promotion(&self) -> Option<PieceKind> {
+        self.promotion
+    }
 }
 
-//todo: implement Display and Debug
\ No newline at end of file
+/// Formats the move in UCI long algebraic notation,
+/// such as `e2e4`, `e1g1` for castling or `e7e8q` for a promotion.
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (o, t) = (self.origin, self.target);
+
+        write!(
+            f, "{}{}",
+            Position {row: o.0, col: o.1},
+            Position {row: t.0, col: t.1},
+        )?;
+
+        if let Some(kind) = self.promotion {
+            write!(f, "{}", kind.as_char().to_ascii_lowercase())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a move in UCI long algebraic notation.
+/// 
+/// This only checks the syntax; use `Board::parse_uci`
+/// to check the move against a position.
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseMoveError::InvalidSyntax(s.to_string());
+
+        if !s.is_ascii() || (s.len() != 4 && s.len() != 5) {
+            return Err(invalid())
+        }
+
+        let origin = Position::from_square(&s[0..2]).ok_or_else(invalid)?;
+        let target = Position::from_square(&s[2..4]).ok_or_else(invalid)?;
+
+        let promotion = match s[4..].chars().next() {
+            Some(c) if c.is_ascii_lowercase() => {
+                let kind = PieceKind::from_char(c)
+                    .filter(|k| PieceKind::PROMOTIONS.contains(k))
+                    .ok_or_else(invalid)?;
+                Some(kind)
+            }
+            Some(_) => return Err(invalid()),
+            None => None,
+        };
+
+        Ok(Move {
+            target: target.as_tuple(),
+            origin: origin.as_tuple(),
+            promotion,
+        })
+    }
+}
+
+impl Board {
+    /// Parses a move in UCI long algebraic notation
+    /// and checks that it is legal in the current position.
+    pub fn parse_uci(&self, s: &str) -> Result<Move, ParseMoveError> {
+        let mov: Move = s.parse()?;
+
+        if self.legal_moves().contains(&mov) {
+            Ok(mov)
+        } else {
+            Err(ParseMoveError::IllegalMove(s.to_string()))
+        }
+    }
+}
+
+/// Describes why a move in UCI notation could not be parsed.
+#[non_exhaustive]
+#[derive(Clone, PartialEq, Debug)]
+pub enum ParseMoveError {
+    /// The text is not two squares optionally followed by a promotion piece.
+    InvalidSyntax(String),
+    /// The move is not legal in the position it was checked against.
+    IllegalMove(String),
+}
+
+impl Error for ParseMoveError {}
+
+impl fmt::Display for ParseMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidSyntax(s) => {
+                write!(f, "'{}' is not a move in coordinate notation", s)
+            }
+            Self::IllegalMove(s) => {
+                write!(f, "'{}' is not a legal move in this position", s)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_display() {
+        assert_eq!(Move::construct((3, 4), (1, 4)).to_string(), "e2e4");
+        assert_eq!(Move::construct((0, 6), (0, 4)).to_string(), "e1g1");
+        assert_eq!(Move::promoting((7, 0), (6, 1), PieceKind::Knight).to_string(), "b7a8n");
+    }
+
+    #[test]
+    fn test_move_from_str() {
+        assert_eq!("e2e4".parse(), Ok(Move::construct((3, 4), (1, 4))));
+        assert_eq!("h2h1q".parse(), Ok(Move::promoting((0, 7), (1, 7), PieceKind::Queen)));
+
+        for bad in ["", "e2", "e2e9", "i2e4", "e2e4k", "e2e4Q", "e2e4qq", "é2e4"].iter() {
+            assert_eq!(
+                bad.parse::<Move>(),
+                Err(ParseMoveError::InvalidSyntax(bad.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_uci() {
+        let board = Board::from_fen(
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 b kq - 0 1"
+        ).unwrap();
+
+        assert_eq!(board.parse_uci("e8c8"), Ok(Move::construct((7, 2), (7, 4))));
+        assert_eq!(board.parse_uci("e8g8"), Err(ParseMoveError::IllegalMove("e8g8".to_string())));
+        assert_eq!(board.parse_uci("b2a1r"), Ok(Move::promoting((0, 0), (1, 1), PieceKind::Rook)));
+        assert_eq!(board.parse_uci("b2a1"), Err(ParseMoveError::IllegalMove("b2a1".to_string())));
+        assert_eq!(board.parse_uci("a2a4"), Err(ParseMoveError::IllegalMove("a2a4".to_string())));
+
+        for mov in board.legal_moves() {
+            assert_eq!(board.parse_uci(&mov.to_string()), Ok(mov));
+        }
+    }
+
+    #[test]
+    fn test_moveset_scores() {
+        let mut moves = MoveSet::from(["a2a3", "b2b3", "c2c3", "d2d3"].iter()
+            .map(|m| m.parse::<Move>().unwrap()));
+        assert_eq!(moves.scores(), &[0, 0, 0, 0]);
+
+        moves.set_score(1, 5);
+        moves.set_score(2, -3);
+        moves.set_score(3, 9);
+
+        let order: Vec<_> = (0..moves.len())
+            .map(|i| moves.pick_best(i).unwrap())
+            .map(|(mov, score)| (mov.to_string(), score))
+            .collect();
+        assert_eq!(order, vec![
+            ("d2d3".to_string(), 9), ("b2b3".to_string(), 5),
+            ("a2a3".to_string(), 0), ("c2c3".to_string(), -3),
+        ]);
+        assert_eq!(moves.score(0), 9);
+        assert_eq!(moves.pick_best(4), None);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,357 @@
+//! Move ordering: deciding which moves the search tries first.
+//!
+//! Alpha-beta prunes the most when the best move comes first, so the
+//! `MovePicker` hands out moves in stages, from those most likely to be
+//! good to those least likely: the hash move, captures that do not lose
+//! material, killer moves, the countermove, the remaining quiet moves by
+//! their history, and finally captures that lose material. Moves are only
+//! scored once their stage is reached, so a cutoff on the hash move costs
+//! no scoring at all.
+
+use crate::board::Board;
+use crate::eval::piece_value;
+use crate::moves::{Move, MoveMarker, MoveSet};
+use crate::pieces::{Colour, PieceKind};
+use crate::search::MAX_DEPTH;
+
+/// History scores are kept within this bound, so recent cutoffs count
+/// for more than old ones.
+const HISTORY_MAX: i32 = 16384;
+
+/// What the search has learned about which quiet moves tend to cause cutoffs.
+///
+/// Kept for a whole search and shared by every node in it.
+pub struct Heuristics {
+    // two quiet moves per ply that recently caused cutoffs there
+    killers: Vec<[Option<Move>; 2]>,
+    // the quiet move that last refuted each move, by that move's origin and target
+    countermoves: Vec<Option<Move>>,
+    // how often each quiet move caused a cutoff, by side, origin and target
+    history: Vec<i32>,
+}
+
+impl Heuristics {
+    pub fn new() -> Self {
+        Heuristics {
+            killers: vec![[None; 2]; MAX_DEPTH as usize + 1],
+            countermoves: vec![None; 64 * 64],
+            history: vec![0; 2 * 64 * 64],
+        }
+    }
+
+    /// Returns the killer moves recorded at the given ply.
+    pub fn killers(&self, ply: usize) -> [Option<Move>; 2] {
+        self.killers.get(ply).copied().unwrap_or_default()
+    }
+
+    /// Returns the move that last refuted `previous`.
+    pub fn countermove(&self, previous: Option<Move>) -> Option<Move> {
+        previous.and_then(|prev| self.countermoves[from_to(&prev)])
+    }
+
+    /// Returns the history score of a quiet move by the given side.
+    pub fn history(&self, colour: Colour, mov: &Move) -> i32 {
+        self.history[colour as usize * 4096 + from_to(mov)]
+    }
+
+    /// Records that a quiet move caused a beta cutoff at the given ply
+    /// and depth, after the quiet moves in `tried` failed to.
+    pub fn reward(&mut self, board: &Board, mov: Move, ply: usize, depth: u32, tried: &[Move]) {
+        if let Some(killers) = self.killers.get_mut(ply) {
+            if killers[0] != Some(mov) {
+                killers[1] = killers[0];
+                killers[0] = Some(mov);
+            }
+        }
+
+        if let Some(prev) = board.previous_move() {
+            self.countermoves[from_to(&prev)] = Some(mov);
+        }
+
+        //deeper cutoffs prune more, so they count for more
+        let bonus = (depth * depth).min(400) as i32;
+        let colour = board.turn();
+        self.adjust_history(colour, &mov, bonus);
+        for failed in tried {
+            self.adjust_history(colour, failed, -bonus);
+        }
+    }
+
+    fn adjust_history(&mut self, colour: Colour, mov: &Move, bonus: i32) {
+        let score = &mut self.history[colour as usize * 4096 + from_to(mov)];
+
+        //scores shrink towards zero the closer they get to the bound
+        *score += bonus - *score * bonus.abs() / HISTORY_MAX;
+    }
+}
+
+impl Default for Heuristics {
+    fn default() -> Self {
+        Heuristics::new()
+    }
+}
+
+/// The stages of the move picker, in the order they are reached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+    HashMove,
+    ScoreCaptures,
+    GoodCaptures,
+    Killers,
+    Countermove,
+    ScoreQuiets,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+/// Hands out the legal moves of a position one at a time, best first.
+pub struct MovePicker {
+    stage: Stage,
+    // every legal move, until the captures and quiets are split off
+    moves: MoveSet,
+    hash_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    countermove: Option<Move>,
+    // moves already handed out in an early stage, to skip later
+    special: Vec<Move>,
+    captures: MoveSet,
+    quiets: MoveSet,
+    bad_captures: Vec<Move>,
+    index: usize,
+}
+
+impl MovePicker {
+    /// Creates a picker over the legal moves of the given position at `ply`.
+    ///
+    /// The hash move, if given and legal, is always handed out first.
+    pub fn new(board: &Board, moves: MoveSet, hash_move: Option<Move>, heuristics: &Heuristics, ply: usize) -> Self {
+        let previous = board.previous_move();
+
+        MovePicker {
+            stage: Stage::HashMove,
+            moves,
+            hash_move,
+            killers: heuristics.killers(ply),
+            countermove: heuristics.countermove(previous),
+            special: Vec::with_capacity(4),
+            captures: MoveSet::from(Vec::<Move>::new()),
+            quiets: MoveSet::from(Vec::<Move>::new()),
+            bad_captures: Vec::new(),
+            index: 0,
+        }
+    }
+
+    /// Returns the next move to try, or None once every move has been handed out.
+    ///
+    /// `board` must be in the position the picker was created for.
+    pub fn next(&mut self, board: &Board, heuristics: &Heuristics) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::HashMove => {
+                    self.stage = Stage::ScoreCaptures;
+                    if let Some(mov) = self.hash_move.filter(|m| self.moves.contains(m)) {
+                        self.special.push(mov);
+                        return Some(mov)
+                    }
+                }
+                Stage::ScoreCaptures => {
+                    self.split(board);
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => match self.captures.pick_best(self.index) {
+                    Some((mov, _)) => {
+                        self.index += 1;
+                        if self.special.contains(&mov) {
+                            continue
+                        }
+                        //captures that lose material wait until the very end
+                        if board.see(&mov) < 0 {
+                            self.bad_captures.push(mov);
+                            continue
+                        }
+                        return Some(mov)
+                    }
+                    None => {
+                        self.stage = Stage::Killers;
+                        self.index = 0;
+                    }
+                },
+                Stage::Killers => {
+                    let killer = self.killers.get(self.index).copied();
+                    self.index += 1;
+
+                    match killer {
+                        Some(Some(mov)) => {
+                            if let Some(mov) = self.take_quiet(mov) {
+                                return Some(mov)
+                            }
+                        }
+                        Some(None) => {}
+                        None => self.stage = Stage::Countermove,
+                    }
+                }
+                Stage::Countermove => {
+                    self.stage = Stage::ScoreQuiets;
+                    if let Some(mov) = self.countermove.and_then(|mov| self.take_quiet(mov)) {
+                        return Some(mov)
+                    }
+                }
+                Stage::ScoreQuiets => {
+                    let colour = board.turn();
+                    for i in 0..self.quiets.len() {
+                        let score = heuristics.history(colour, &self.quiets[i]);
+                        self.quiets.set_score(i, score);
+                    }
+
+                    self.stage = Stage::Quiets;
+                    self.index = 0;
+                }
+                Stage::Quiets => match self.quiets.pick_best(self.index) {
+                    Some((mov, _)) => {
+                        self.index += 1;
+                        if !self.special.contains(&mov) {
+                            return Some(mov)
+                        }
+                    }
+                    None => {
+                        self.stage = Stage::BadCaptures;
+                        self.index = 0;
+                    }
+                },
+                Stage::BadCaptures => match self.bad_captures.get(self.index) {
+                    Some(&mov) => {
+                        self.index += 1;
+                        return Some(mov)
+                    }
+                    None => self.stage = Stage::Done,
+                },
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /// Splits the moves into captures, scored most valuable victim first
+    /// and then least valuable attacker, and quiet moves.
+    /// Promotions count as captures of the value they gain.
+    fn split(&mut self, board: &Board) {
+        let moves = std::mem::replace(&mut self.moves, MoveSet::from(Vec::<Move>::new()));
+        let (captures, quiets): (Vec<Move>, Vec<Move>) = moves.into_iter()
+            .partition(|mov| is_tactical(board, mov));
+
+        self.captures = MoveSet::from(captures);
+        for i in 0..self.captures.len() {
+            let score = mvv_lva(board, &self.captures[i]);
+            self.captures.set_score(i, score);
+        }
+        self.quiets = MoveSet::from(quiets);
+    }
+
+    /// Hands out a killer or countermove if it is a legal quiet move
+    /// not already handed out.
+    fn take_quiet(&mut self, mov: Move) -> Option<Move> {
+        if self.special.contains(&mov) || !self.quiets.contains(&mov) {
+            return None
+        }
+
+        self.special.push(mov);
+        Some(mov)
+    }
+}
+
+/// Returns whether a move changes the material on the board:
+/// a capture or a promotion.
+pub fn is_tactical(board: &Board, mov: &Move) -> bool {
+    mov.promotion().is_some() || board.captured(mov).is_some()
+}
+
+/// Scores a capture by the value of what it takes, then by how little
+/// the capturing piece is worth.
+pub(crate) fn mvv_lva(board: &Board, mov: &Move) -> i32 {
+    let (row, col) = mov.origin();
+    let attacker = board[row][col].kind().map_or(0, piece_value);
+    let victim = board.captured(mov).map_or(0, piece_value);
+    let promotion = mov.promotion().map_or(0, |kind| piece_value(kind) - piece_value(PieceKind::Pawn));
+
+    (victim + promotion) * 10 - attacker
+}
+
+/// Indexes a move by its origin and target squares.
+fn from_to(mov: &Move) -> usize {
+    let (o, t) = (mov.origin(), mov.target());
+    (o.0 * 8 + o.1) * 64 + t.0 * 8 + t.1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picked(board: &Board, hash_move: Option<&str>, heuristics: &Heuristics) -> Vec<String> {
+        let hash_move = hash_move.map(|m| board.parse_uci(m).unwrap());
+        let mut picker = MovePicker::new(board, board.legal_moves(), hash_move, heuristics, 0);
+        let mut moves = Vec::new();
+
+        while let Some(mov) = picker.next(board, heuristics) {
+            moves.push(mov.to_string());
+        }
+
+        moves
+    }
+
+    #[test]
+    fn test_picker_yields_every_move_once() {
+        let board = Board::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"
+        ).unwrap();
+        let moves = picked(&board, Some("e2a6"), &Heuristics::new());
+
+        let mut sorted = moves.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted.len(), 48);
+        assert_eq!(moves.len(), 48);
+        assert_eq!(moves[0], "e2a6");
+    }
+
+    #[test]
+    fn test_picker_capture_order() {
+        //the rook can take a free queen, the pawn a defended knight,
+        //and the queen a pawn defended by a pawn
+        let board = Board::from_fen("4k3/8/2p5/1p1n3q/4P3/8/1Q6/4K2R w - - 0 1").unwrap();
+        let moves = picked(&board, None, &Heuristics::new());
+
+        assert_eq!(&moves[..2], &["h1h5", "e4d5"]);
+        assert_eq!(moves.last().unwrap(), "b2b5");
+    }
+
+    #[test]
+    fn test_picker_quiet_order() {
+        let board = Board::init();
+        let mut heuristics = Heuristics::new();
+        let mov = |m: &str| board.parse_uci(m).unwrap();
+
+        heuristics.reward(&board, mov("g1f3"), 0, 4, &[mov("a2a3")]);
+        heuristics.reward(&board, mov("d2d4"), 0, 2, &[]);
+        let moves = picked(&board, Some("e2e4"), &heuristics);
+
+        //hash move, then killers newest first, then the rest by history
+        assert_eq!(&moves[..3], &["e2e4", "d2d4", "g1f3"]);
+        assert_eq!(moves.last().unwrap(), "a2a3");
+        assert!(heuristics.history(Colour::White, &mov("g1f3")) > 0);
+        assert!(heuristics.history(Colour::White, &mov("a2a3")) < 0);
+    }
+
+    #[test]
+    fn test_picker_countermove() {
+        let mut board = Board::init();
+        board.move_piece(board.parse_uci("e2e4").unwrap()).unwrap();
+
+        let mut heuristics = Heuristics::new();
+        let reply = board.parse_uci("c7c5").unwrap();
+        heuristics.reward(&board, reply, 5, 3, &[]);
+
+        //no killers at ply 0, but the countermove to e2e4 comes before other quiets
+        assert_eq!(heuristics.countermove(board.history().last().map(|r| r.mov)), Some(reply));
+        assert_eq!(picked(&board, None, &heuristics)[0], "c7c5");
+    }
+}
@@ -0,0 +1,115 @@
+//! Move path enumeration, for validating the move generator
+//! against known node counts.
+
+use crate::board::Board;
+
+impl Board {
+    /// Counts the leaf nodes of the legal move tree to the given depth.
+    pub fn perft(&self, depth: u32) -> u64 {
+        self.clone().perft_inner(depth)
+    }
+
+    fn perft_inner(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1
+        }
+
+        let moves = self.legal_moves();
+
+        //the last ply needs no playing out, only counting
+        if depth == 1 {
+            return moves.len() as u64
+        }
+
+        let mut nodes = 0;
+        for mov in moves {
+            self.play(mov);
+            nodes += self.perft_inner(depth - 1);
+            self.unmake_move();
+        }
+
+        nodes
+    }
+
+    /// Runs perft and prints the node count below each root move,
+    /// followed by the total, in the format most engines use.
+    /// 
+    /// Returns the total node count.
+    pub fn divide(&self, depth: u32) -> u64 {
+        let mut board = self.clone();
+        let mut total = 0;
+
+        for mov in self.legal_moves() {
+            board.play(mov);
+            let nodes = board.perft_inner(depth.saturating_sub(1));
+            board.unmake_move();
+
+            total += nodes;
+
+            println!("{}: {}", mov, nodes);
+        }
+
+        println!("\nNodes searched: {}", total);
+
+        total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::STARTING_FEN;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+    const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+    const POSITION_4_MIRRORED: &str = "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+    const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+    const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+    fn check(fen: &str, counts: &[u64]) {
+        let board = Board::from_fen(fen).unwrap();
+
+        for (depth, &count) in counts.iter().enumerate() {
+            assert_eq!(board.perft(depth as u32 + 1), count, "{} at depth {}", fen, depth + 1);
+        }
+    }
+
+    #[test]
+    fn test_perft_start() {
+        check(STARTING_FEN, &[20, 400, 8902, 197281]);
+    }
+
+    #[test]
+    fn test_perft_kiwipete() {
+        check(KIWIPETE, &[48, 2039, 97862]);
+    }
+
+    #[test]
+    fn test_perft_position_3() {
+        check(POSITION_3, &[14, 191, 2812, 43238]);
+    }
+
+    #[test]
+    fn test_perft_position_4() {
+        check(POSITION_4, &[6, 264, 9467]);
+        check(POSITION_4_MIRRORED, &[6, 264, 9467]);
+    }
+
+    #[test]
+    fn test_perft_position_5() {
+        check(POSITION_5, &[44, 1486, 62379]);
+    }
+
+    #[test]
+    fn test_perft_position_6() {
+        check(POSITION_6, &[46, 2079, 89890]);
+    }
+
+    #[test]
+    fn test_divide_total() {
+        let board = Board::from_fen(KIWIPETE).unwrap();
+
+        assert_eq!(board.divide(2), 2039);
+    }
+}
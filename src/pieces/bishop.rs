@@ -5,7 +5,7 @@ use super::*;
 
 pub const POINTS: u8 = 3;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Bishop {
     colour: Colour,
     pos: Position,
@@ -13,11 +13,11 @@ pub struct Bishop {
 
 impl Bishop {
     pub fn config(row: usize, col: usize, colour: Colour) -> Self {
-        let pos = Position {row: row, col: col};
+        let pos = Position {row, col};
 
         Self {
-            colour: colour,
-            pos: pos,
+            colour,
+            pos,
         }
     }
 }
@@ -34,6 +34,53 @@ impl Piece for Bishop {
     }
 
     fn evaluate_moves(&self, board: &Board) -> MoveSet {
-        unimplemented!()
+        slide(board, self.pos, self.colour, &DIAGONALS)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::MoveMarker;
+
+    #[test]
+    fn test_bishop_open_board() {
+        let mut board = Board::empty();
+        board.place(PieceType::Bishop(Bishop::config(3, 3, Colour::White)));
+
+        let moves = board[3][3].inner().unwrap().evaluate_moves(&board);
+
+        assert_eq!(moves.len(), 13);
+        assert!(!moves.is_checking());
+    }
+
+    #[test]
+    fn test_bishop_blocked_and_captures() {
+        use Colour::*;
+
+        let mut board = Board::empty();
+        board.place(PieceType::Bishop(Bishop::config(0, 2, White)));
+        board.place(PieceType::Pawn(Pawn::config(1, 1, White)));
+        board.place(PieceType::Pawn(Pawn::config(3, 5, Black)));
+
+        let moves = board[0][2].inner().unwrap().evaluate_moves(&board);
+        let mut targets: Vec<_> = moves.iter().map(|m| m.target()).collect();
+        targets.sort_unstable();
+
+        assert_eq!(targets, vec![(1, 3), (2, 4), (3, 5)]);
+    }
+
+    #[test]
+    fn test_bishop_checks_king() {
+        use Colour::*;
+
+        let mut board = Board::empty();
+        board.place(PieceType::Bishop(Bishop::config(7, 7, Black)));
+        board.place(PieceType::King(King::config(2, 2, White)));
+
+        let moves = board[7][7].inner().unwrap().evaluate_moves(&board);
+
+        assert!(moves.is_checking());
+        assert_eq!(moves.len(), 4);
+    }
+}
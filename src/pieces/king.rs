@@ -1,9 +1,9 @@
 use crate::pieces::Piece;
-use crate::moves::MoveSet;
+use crate::moves::{MoveSet, Move};
 use crate::board::Board;
 use super::*;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct King {
     colour: Colour,
     pos: Position,
@@ -11,11 +11,11 @@ pub struct King {
 
 impl King {
     pub fn config(row: usize, col: usize, colour: Colour) -> Self {
-        let pos = Position {row: row, col: col};
+        let pos = Position {row, col};
 
         Self {
-            colour: colour,
-            pos: pos,
+            colour,
+            pos,
         }
     }
 }
@@ -32,6 +32,187 @@ impl Piece for King {
     }
     
     fn evaluate_moves(&self, board: &Board) -> MoveSet {
-        unimplemented!()
+        let mut moves = step(board, self.pos, self.colour, &KING_STEPS);
+
+        let mut castles: Vec<Move> = Vec::new();
+        if self.can_castle(board, true) {
+            castles.push(Move::construct((self.pos.row, 6), self.pos.as_tuple()));
+        }
+        if self.can_castle(board, false) {
+            castles.push(Move::construct((self.pos.row, 2), self.pos.as_tuple()));
+        }
+
+        if !castles.is_empty() {
+            let checking = moves.is_checking();
+            moves = MoveSet::from(moves.into_iter().chain(castles));
+            if checking {
+                moves.mark_checking();
+            }
+        }
+
+        moves
+    }
+}
+
+impl King {
+    /// Returns whether the king can castle to the given side right now.
+    /// 
+    /// Requires the castling right, a friendly rook in the corner,
+    /// empty squares between them, and that the king is not in check
+    /// and does not pass through or land on an attacked square.
+    fn can_castle(&self, board: &Board, kingside: bool) -> bool {
+        let rights = board.castling_rights();
+        let has_right = if kingside {
+            rights.kingside(self.colour)
+        } else {
+            rights.queenside(self.colour)
+        };
+
+        let home = match self.colour {
+            Colour::White => 0,
+            Colour::Black => 7,
+        };
+
+        if !has_right || self.pos != (home, 4) {
+            return false
+        }
+
+        let (rook_col, between, crossed): (usize, &[usize], &[usize]) = if kingside {
+            (7, &[5, 6], &[4, 5, 6])
+        } else {
+            (0, &[1, 2, 3], &[4, 3, 2])
+        };
+
+        match board[home][rook_col] {
+            PieceType::Rook(r) if r.colour() == self.colour => {}
+            _ => return false,
+        }
+
+        if between.iter().any(|&col| !board[home][col].is_empty()) {
+            return false
+        }
+
+        let enemy = self.colour.opposite();
+
+        !crossed.iter().any(|&col| board.is_square_attacked(Position {row: home, col}, enemy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::CastlingRights;
+    use crate::moves::MoveMarker;
+
+    fn castling_board() -> Board {
+        use Colour::*;
+
+        let mut board = Board::empty();
+        board.place(PieceType::King(King::config(0, 4, White)));
+        board.place(PieceType::Rook(Rook::config(0, 0, White)));
+        board.place(PieceType::Rook(Rook::config(0, 7, White)));
+        board.place(PieceType::King(King::config(7, 4, Black)));
+        board.set_castling_rights(CastlingRights::all());
+
+        board
+    }
+
+    fn targets(board: &Board, row: usize, col: usize) -> Vec<(usize, usize)> {
+        let mut targets: Vec<_> = board[row][col].inner().unwrap()
+            .evaluate_moves(board)
+            .iter()
+            .map(|m| m.target())
+            .collect();
+        targets.sort_unstable();
+
+        targets
+    }
+
+    #[test]
+    fn test_king_steps() {
+        let mut board = Board::empty();
+        board.place(PieceType::King(King::config(3, 3, Colour::White)));
+        assert_eq!(targets(&board, 3, 3).len(), 8);
+
+        let mut board = Board::empty();
+        board.place(PieceType::King(King::config(0, 0, Colour::White)));
+        assert_eq!(targets(&board, 0, 0), vec![(0, 1), (1, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn test_king_start_position() {
+        let board = Board::init();
+
+        assert!(targets(&board, 0, 4).is_empty());
+        assert!(targets(&board, 7, 4).is_empty());
+    }
+
+    #[test]
+    fn test_castling_both_sides() {
+        let board = castling_board();
+        let targets = targets(&board, 0, 4);
+
+        assert!(targets.contains(&(0, 6)));
+        assert!(targets.contains(&(0, 2)));
+        assert_eq!(targets.len(), 7);
+    }
+
+    #[test]
+    fn test_castling_needs_rights() {
+        let mut board = castling_board();
+        board.set_castling_rights(CastlingRights {
+            white_kingside: false,
+            ..CastlingRights::all()
+        });
+        let targets = targets(&board, 0, 4);
+
+        assert!(!targets.contains(&(0, 6)));
+        assert!(targets.contains(&(0, 2)));
+    }
+
+    #[test]
+    fn test_castling_blocked() {
+        use Colour::*;
+
+        let mut board = castling_board();
+        board.place(PieceType::Knight(Knight::config(0, 1, White)));
+        board.place(PieceType::Bishop(Bishop::config(0, 5, Black)));
+        let targets = targets(&board, 0, 4);
+
+        assert!(!targets.contains(&(0, 6)));
+        assert!(!targets.contains(&(0, 2)));
+    }
+
+    #[test]
+    fn test_no_castling_out_of_check() {
+        let mut board = castling_board();
+        board.place(PieceType::Rook(Rook::config(5, 4, Colour::Black)));
+        let targets = targets(&board, 0, 4);
+
+        assert!(!targets.contains(&(0, 6)));
+        assert!(!targets.contains(&(0, 2)));
+    }
+
+    #[test]
+    fn test_no_castling_through_or_into_attack() {
+        let mut board = castling_board();
+        //attacks f1, which the king crosses when castling kingside
+        board.place(PieceType::Rook(Rook::config(5, 5, Colour::Black)));
+        //attacks c1, which the king lands on when castling queenside
+        board.place(PieceType::Bishop(Bishop::config(2, 0, Colour::Black)));
+        let targets = targets(&board, 0, 4);
+
+        assert!(!targets.contains(&(0, 6)));
+        assert!(!targets.contains(&(0, 2)));
+    }
+
+    #[test]
+    fn test_queenside_rook_may_pass_attacked_square() {
+        let mut board = castling_board();
+        //b1 is attacked, but only the rook crosses it
+        board.place(PieceType::Rook(Rook::config(5, 1, Colour::Black)));
+        let targets = targets(&board, 0, 4);
+
+        assert!(targets.contains(&(0, 2)));
     }
 }
\ No newline at end of file
@@ -5,7 +5,7 @@ use super::*;
 
 pub const POINTS: u8 = 3;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Knight {
     colour: Colour,
     pos: Position,
@@ -13,11 +13,11 @@ pub struct Knight {
 
 impl Knight {
     pub fn config(row: usize, col: usize, colour: Colour) -> Self {
-        let pos = Position {row: row, col: col};
+        let pos = Position {row, col};
 
         Self {
-            colour: colour,
-            pos: pos,
+            colour,
+            pos,
         }
     }
 }
@@ -34,6 +34,62 @@ impl Piece for Knight {
     }
     
     fn evaluate_moves(&self, board: &Board) -> MoveSet {
-        unimplemented!()
+        step(board, self.pos, self.colour, &KNIGHT_JUMPS)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::MoveMarker;
+
+    #[test]
+    fn test_knight_centre() {
+        let mut board = Board::empty();
+        board.place(PieceType::Knight(Knight::config(3, 3, Colour::White)));
+
+        let moves = board[3][3].inner().unwrap().evaluate_moves(&board);
+
+        assert_eq!(moves.len(), 8);
+        assert!(!moves.is_checking());
+    }
+
+    #[test]
+    fn test_knight_corner() {
+        let mut board = Board::empty();
+        board.place(PieceType::Knight(Knight::config(0, 0, Colour::Black)));
+
+        let moves = board[0][0].inner().unwrap().evaluate_moves(&board);
+        let mut targets: Vec<_> = moves.iter().map(|m| m.target()).collect();
+        targets.sort_unstable();
+
+        assert_eq!(targets, vec![(1, 2), (2, 1)]);
+    }
+
+    #[test]
+    fn test_knight_start_position() {
+        let board = Board::init();
+
+        let moves = board[0][1].inner().unwrap().evaluate_moves(&board);
+        let mut targets: Vec<_> = moves.iter().map(|m| m.target()).collect();
+        targets.sort_unstable();
+
+        assert_eq!(targets, vec![(2, 0), (2, 2)]);
+    }
+
+    #[test]
+    fn test_knight_captures_and_checks() {
+        use Colour::*;
+
+        let mut board = Board::empty();
+        board.place(PieceType::Knight(Knight::config(0, 7, White)));
+        board.place(PieceType::Rook(Rook::config(2, 6, Black)));
+        board.place(PieceType::King(King::config(1, 5, Black)));
+
+        let moves = board[0][7].inner().unwrap().evaluate_moves(&board);
+        let targets: Vec<_> = moves.iter().map(|m| m.target()).collect();
+
+        assert!(moves.is_checking());
+        assert_eq!(targets, vec![(2, 6)]);
+    }
+}
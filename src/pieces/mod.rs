@@ -5,7 +5,9 @@ pub mod bishop;
 pub mod queen;
 pub mod king;
 
-use crate::moves::MoveSet;
+use std::fmt;
+
+use crate::moves::{MoveSet, Move};
 use crate::board::Board;
 
 pub use pawn::Pawn;
@@ -22,8 +24,51 @@ pub trait Piece {
     fn evaluate_moves(&self, board: &Board) -> MoveSet;
 }
 
+/// The kind of a piece, independent of its colour and position.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PieceKind {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+impl PieceKind {
+    /// The kinds a pawn may promote to, most valuable first.
+    pub const PROMOTIONS: [PieceKind; 4] = [
+        PieceKind::Queen, PieceKind::Rook, PieceKind::Bishop, PieceKind::Knight
+    ];
+
+    /// Returns the uppercase letter used for the kind in move notation.
+    pub fn as_char(&self) -> char {
+        match self {
+            PieceKind::Pawn => 'P',
+            PieceKind::Knight => 'N',
+            PieceKind::Bishop => 'B',
+            PieceKind::Rook => 'R',
+            PieceKind::Queen => 'Q',
+            PieceKind::King => 'K',
+        }
+    }
+
+    /// Returns the kind denoted by the given letter in either case.
+    pub fn from_char(c: char) -> Option<PieceKind> {
+        match c.to_ascii_uppercase() {
+            'P' => Some(PieceKind::Pawn),
+            'N' => Some(PieceKind::Knight),
+            'B' => Some(PieceKind::Bishop),
+            'R' => Some(PieceKind::Rook),
+            'Q' => Some(PieceKind::Queen),
+            'K' => Some(PieceKind::King),
+            _ => None,
+        }
+    }
+}
+
 /// Defines the type of Piece.
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum PieceType {
     Pawn(Pawn),
     Rook(Rook),
@@ -69,6 +114,53 @@ impl PieceType {
         false
     }
 
+    #[inline]
+    pub fn is_pawn(&self) -> bool {
+        if let PieceType::Pawn(_) = self {
+            return true
+        }
+        false
+    }
+
+    /// Returns the kind of the PieceType, or None if it is Empty.
+    pub fn kind(&self) -> Option<PieceKind> {
+        match self {
+            PieceType::Pawn(_) => Some(PieceKind::Pawn),
+            PieceType::Rook(_) => Some(PieceKind::Rook),
+            PieceType::Knight(_) => Some(PieceKind::Knight),
+            PieceType::Bishop(_) => Some(PieceKind::Bishop),
+            PieceType::Queen(_) => Some(PieceKind::Queen),
+            PieceType::King(_) => Some(PieceKind::King),
+            PieceType::Empty => None,
+        }
+    }
+
+    /// Constructs a piece of the given kind and colour at the given square.
+    pub fn from_kind(kind: PieceKind, row: usize, col: usize, colour: Colour) -> PieceType {
+        match kind {
+            PieceKind::Pawn => PieceType::Pawn(Pawn::config(row, col, colour)),
+            PieceKind::Rook => PieceType::Rook(Rook::config(row, col, colour)),
+            PieceKind::Knight => PieceType::Knight(Knight::config(row, col, colour)),
+            PieceKind::Bishop => PieceType::Bishop(Bishop::config(row, col, colour)),
+            PieceKind::Queen => PieceType::Queen(Queen::config(row, col, colour)),
+            PieceKind::King => PieceType::King(King::config(row, col, colour)),
+        }
+    }
+
+    /// Returns the same piece relocated to the given square,
+    /// or Empty if the PieceType is Empty.
+    pub fn moved_to(&self, row: usize, col: usize) -> PieceType {
+        match self {
+            PieceType::Pawn(p) => PieceType::Pawn(Pawn::config(row, col, p.colour())),
+            PieceType::Rook(r) => PieceType::Rook(Rook::config(row, col, r.colour())),
+            PieceType::Knight(n) => PieceType::Knight(Knight::config(row, col, n.colour())),
+            PieceType::Bishop(b) => PieceType::Bishop(Bishop::config(row, col, b.colour())),
+            PieceType::Queen(q) => PieceType::Queen(Queen::config(row, col, q.colour())),
+            PieceType::King(k) => PieceType::King(King::config(row, col, k.colour())),
+            PieceType::Empty => PieceType::Empty,
+        }
+    }
+
     pub fn inner(&self) -> Option<&dyn Piece> {
         use PieceType::*;
 
@@ -83,6 +175,23 @@ impl PieceType {
         }
     }
 
+    /// Constructs the piece denoted by the given notation at the given square,
+    /// the inverse of `as_char`.
+    pub fn from_char(c: char, row: usize, col: usize) -> Option<PieceType> {
+        let colour = if c.is_ascii_uppercase() { Colour::White } else { Colour::Black };
+        let kind = match c.to_ascii_lowercase() {
+            'p' => PieceKind::Pawn,
+            'n' => PieceKind::Knight,
+            'b' => PieceKind::Bishop,
+            'r' => PieceKind::Rook,
+            'q' => PieceKind::Queen,
+            'k' => PieceKind::King,
+            _ => return None,
+        };
+
+        Some(PieceType::from_kind(kind, row, col, colour))
+    }
+
     /// Returns the corresponding notation for the piece.
     pub fn as_char(&self) -> char {
         use PieceType::*;
@@ -136,7 +245,18 @@ pub enum Colour {
     White,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+impl Colour {
+    /// Returns the other colour.
+    #[inline]
+    pub fn opposite(&self) -> Colour {
+        match self {
+            Colour::Black => Colour::White,
+            Colour::White => Colour::Black,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Position {
     pub row: usize,
     pub col: usize,
@@ -146,4 +266,146 @@ impl Position {
     pub fn as_tuple(&self) -> (usize, usize) {
         (self.row, self.col)
     }
+
+    /// Returns the position offset by the given number of rows and columns,
+    /// or None if it would fall off the board.
+    pub fn offset(&self, rows: isize, cols: isize) -> Option<Position> {
+        let row = self.row as isize + rows;
+        let col = self.col as isize + cols;
+
+        if (0..8).contains(&row) && (0..8).contains(&col) {
+            Some(Position {row: row as usize, col: col as usize})
+        } else {
+            None
+        }
+    }
+
+    /// Parses a square in algebraic notation, such as `e4`.
+    pub fn from_square(square: &str) -> Option<Position> {
+        let mut chars = square.chars();
+        let file = chars.next()?;
+        let rank = chars.next()?;
+
+        if chars.next().is_some() || !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+            return None
+        }
+
+        Some(Position {
+            row: rank as usize - '1' as usize,
+            col: file as usize - 'a' as usize,
+        })
+    }
+}
+
+/// Formats the Position as a square in algebraic notation, such as `e4`.
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.col as u8) as char, self.row + 1)
+    }
+}
+
+impl PartialEq<(usize, usize)> for Position {
+    fn eq(&self, other: &(usize, usize)) -> bool {
+        self.as_tuple() == *other
+    }
+}
+
+/// The directions a rook slides in, as (row, col) steps.
+pub(crate) const ORTHOGONALS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// The directions a bishop slides in, as (row, col) steps.
+pub(crate) const DIAGONALS: [(isize, isize); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+/// Every direction on the board, as (row, col) steps: a rook's and a bishop's together.
+pub(crate) const ALL_DIRECTIONS: [(isize, isize); 8] = [
+    (1, 0), (-1, 0), (0, 1), (0, -1),
+    (1, 1), (1, -1), (-1, 1), (-1, -1),
+];
+
+/// The jumps a knight can make, as (row, col) steps.
+pub(crate) const KNIGHT_JUMPS: [(isize, isize); 8] = [
+    (2, 1), (2, -1), (-2, 1), (-2, -1),
+    (1, 2), (1, -2), (-1, 2), (-1, -2),
+];
+
+/// The single steps a king can make, one in every direction.
+pub(crate) const KING_STEPS: [(isize, isize); 8] = ALL_DIRECTIONS;
+
+/// Generates the moves of a stepping piece by trying each offset once.
+/// 
+/// Offsets landing on a piece of the same colour are skipped.
+/// Landing on the enemy king marks the MoveSet as checking instead of
+/// generating a capture.
+pub(crate) fn step(
+    board: &Board, 
+    origin: Position, 
+    colour: Colour, 
+    offsets: &[(isize, isize)]
+) -> MoveSet {
+    let mut moves = Vec::new();
+    let mut checking = false;
+
+    for next in offsets.iter().filter_map(|&(dr, dc)| origin.offset(dr, dc)) {
+        let target = board[next.row][next.col];
+
+        match target.colour() {
+            Some(c) if c == colour => {}
+            Some(_) if target.is_king() => checking = true,
+            _ => moves.push(Move::construct(next.as_tuple(), origin.as_tuple())),
+        }
+    }
+
+    let mut moveset = MoveSet::from(moves);
+    if checking {
+        moveset.mark_checking();
+    }
+
+    moveset
+}
+
+/// Generates the moves of a sliding piece by walking each direction
+/// until it runs off the board or hits another piece.
+/// 
+/// A ray that hits a piece of the opposite colour includes its capture,
+/// except for the enemy king, which instead marks the MoveSet as checking.
+pub(crate) fn slide(
+    board: &Board, 
+    origin: Position, 
+    colour: Colour, 
+    directions: &[(isize, isize)]
+) -> MoveSet {
+    let mut moves = Vec::new();
+    let mut checking = false;
+
+    for &(dr, dc) in directions {
+        let mut current = origin;
+
+        while let Some(next) = current.offset(dr, dc) {
+            let target = board[next.row][next.col];
+
+            match target.colour() {
+                None => {
+                    moves.push(Move::construct(next.as_tuple(), origin.as_tuple()));
+                }
+                Some(c) if c != colour => {
+                    if target.is_king() {
+                        checking = true;
+                    } else {
+                        moves.push(Move::construct(next.as_tuple(), origin.as_tuple()));
+                    }
+                    break
+                }
+                Some(_) => break,
+            }
+
+            current = next;
+        }
+    }
+
+    let mut moveset = MoveSet::from(moves);
+    if checking {
+        moveset.mark_checking();
+    }
+
+    moveset
 }
\ No newline at end of file
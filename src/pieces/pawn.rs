@@ -5,7 +5,7 @@ use super::*;
 
 pub const POINTS: u8 = 1;
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Pawn {
     colour: Colour,
     pos: Position,
@@ -13,11 +13,11 @@ pub struct Pawn {
 
 impl Pawn {
     pub fn config(row: usize, col: usize, colour: Colour) -> Self {
-        let pos = Position {row: row, col: col};
+        let pos = Position {row, col};
 
         Self {
-            colour: colour,
-            pos: pos,
+            colour,
+            pos,
         }
     }
 }
@@ -36,55 +36,190 @@ impl Piece for Pawn {
     fn evaluate_moves(&self, board: &Board) -> MoveSet {
         use Colour::*;
 
-        let o = self.pos.as_tuple();
+        let (dir, start_row) = match self.colour {
+            White => (1, 1),
+            Black => (-1, 6),
+        };
+
         let mut moves = Vec::new();
+        let mut checking = false;
 
-        match self.colour {
-            White => {
-                if board[o.0 + 1][o.1].is_empty() {
-                    moves.push(Move::construct(o, (o.0 + 1, o.1)))
-                }
-                if !board[o.0 + 1][o.1 + 1].is_empty() {
-                    if board[o.0 + 1][o.1 + 1].colour() != Some(White) {
-                        if board[o.0 + 1][o.1 + 1].is_king() {
-                            return MoveSet::check()
-                        }
-                        moves.push(Move::construct(o, (o.0 + 1, o.1 + 1)))
-                    }
-                }
-                if !board[o.0 + 1][o.1 - 1].is_empty() {
-                    if board[o.0 + 1][o.1 - 1].colour() != Some(White) { 
-                        if board[o.0 + 1][o.1 - 1].is_king() {
-                            return MoveSet::check()
-                        }                   
-                        moves.push(Move::construct(o, (o.0 + 1, o.1 - 1)))
+        //single and double pushes onto empty squares
+        if let Some(one) = self.pos.offset(dir, 0) {
+            if board[one.row][one.col].is_empty() {
+                self.push(&mut moves, one);
+
+                if self.pos.row == start_row {
+                    //a pawn on its starting rank always has two ranks ahead of it
+                    let two = one.offset(dir, 0).unwrap();
+                    if board[two.row][two.col].is_empty() {
+                        self.push(&mut moves, two);
                     }
                 }
             }
+        }
 
-            Black => {
-                if board[o.0 - 1][o.1].is_empty() {
-                    moves.push(Move::construct(o, (o.0 + 1, o.1)))
-                }
-                if !board[o.0 - 1][o.1 + 1].is_empty() {
-                    if board[o.0 - 1][o.1 + 1].colour() != Some(Black) {
-                        if board[o.0 - 1][o.1 + 1].is_king() {
-                            return MoveSet::check()
+        //diagonal captures, including en passant
+        for &dc in [-1, 1].iter() {
+            if let Some(diag) = self.pos.offset(dir, dc) {
+                let target = board[diag.row][diag.col];
+
+                match target.colour() {
+                    Some(c) if c != self.colour => {
+                        if target.is_king() {
+                            checking = true;
+                        } else {
+                            self.push(&mut moves, diag);
                         }
-                        moves.push(Move::construct(o, (o.0 + 1, o.1 + 1)))
                     }
-                }
-                if !board[o.0 - 1][o.1 - 1].is_empty() {
-                    if board[o.0 - 1][o.1 - 1].colour() != Some(Black) {  
-                        if board[o.0 - 1][o.1 - 1].is_king() {
-                            return MoveSet::check()
-                        }                  
-                        moves.push(Move::construct(o, (o.0 + 1, o.1 - 1)))
+                    None if board.en_passant() == Some(diag) => {
+                        self.push(&mut moves, diag);
                     }
+                    _ => {}
                 }
             }
         }
-        
-        MoveSet::from(moves)
+
+        let mut moveset = MoveSet::from(moves);
+        if checking {
+            moveset.mark_checking();
+        }
+
+        moveset
+    }
+}
+
+impl Pawn {
+    /// Adds a move to `target`, expanding it into every promotion
+    /// if the pawn reaches the last rank.
+    fn push(&self, moves: &mut Vec<Move>, target: Position) {
+        let last_row = match self.colour {
+            Colour::White => 7,
+            Colour::Black => 0,
+        };
+
+        if target.row == last_row {
+            for &kind in PieceKind::PROMOTIONS.iter() {
+                moves.push(Move::promoting(target.as_tuple(), self.pos.as_tuple(), kind));
+            }
+        } else {
+            moves.push(Move::construct(target.as_tuple(), self.pos.as_tuple()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::MoveMarker;
+
+    fn moves(board: &Board, row: usize, col: usize) -> Vec<Move> {
+        board[row][col].inner().unwrap()
+            .evaluate_moves(board)
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    fn targets(board: &Board, row: usize, col: usize) -> Vec<(usize, usize)> {
+        let mut targets: Vec<_> = moves(board, row, col).iter()
+            .map(|m| m.target())
+            .collect();
+        targets.sort_unstable();
+
+        targets
+    }
+
+    #[test]
+    fn test_pawn_pushes() {
+        let board = Board::init();
+
+        assert_eq!(targets(&board, 1, 4), vec![(2, 4), (3, 4)]);
+        assert_eq!(targets(&board, 6, 4), vec![(4, 4), (5, 4)]);
+    }
+
+    #[test]
+    fn test_pawn_single_push_off_start() {
+        let mut board = Board::empty();
+        board.place(PieceType::Pawn(Pawn::config(2, 4, Colour::White)));
+        board.place(PieceType::Pawn(Pawn::config(5, 3, Colour::Black)));
+
+        assert_eq!(targets(&board, 2, 4), vec![(3, 4)]);
+        assert_eq!(targets(&board, 5, 3), vec![(4, 3)]);
+    }
+
+    #[test]
+    fn test_pawn_blocked() {
+        use Colour::*;
+
+        let mut board = Board::empty();
+        board.place(PieceType::Pawn(Pawn::config(1, 0, White)));
+        board.place(PieceType::Knight(Knight::config(2, 0, Black)));
+        board.place(PieceType::Pawn(Pawn::config(1, 7, White)));
+        board.place(PieceType::Knight(Knight::config(3, 7, Black)));
+
+        assert!(targets(&board, 1, 0).is_empty());
+        assert_eq!(targets(&board, 1, 7), vec![(2, 7)]);
+    }
+
+    #[test]
+    fn test_pawn_edge_captures() {
+        use Colour::*;
+
+        let mut board = Board::empty();
+        board.place(PieceType::Pawn(Pawn::config(3, 0, White)));
+        board.place(PieceType::Rook(Rook::config(4, 1, Black)));
+        board.place(PieceType::Pawn(Pawn::config(4, 7, Black)));
+        board.place(PieceType::Bishop(Bishop::config(3, 6, White)));
+        board.place(PieceType::Bishop(Bishop::config(3, 7, White)));
+
+        assert_eq!(targets(&board, 3, 0), vec![(4, 0), (4, 1)]);
+        assert_eq!(targets(&board, 4, 7), vec![(3, 6)]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_pawn_en_passant() {
+        use Colour::*;
+
+        let mut board = Board::empty();
+        board.place(PieceType::Pawn(Pawn::config(4, 4, White)));
+        board.place(PieceType::Pawn(Pawn::config(4, 3, Black)));
+        board.place(PieceType::Pawn(Pawn::config(4, 5, Black)));
+
+        assert_eq!(targets(&board, 4, 4), vec![(5, 4)]);
+
+        board.set_en_passant(Some(Position {row: 5, col: 3}));
+        assert_eq!(targets(&board, 4, 4), vec![(5, 3), (5, 4)]);
+    }
+
+    #[test]
+    fn test_pawn_promotion() {
+        use Colour::*;
+
+        let mut board = Board::empty();
+        board.place(PieceType::Pawn(Pawn::config(6, 0, White)));
+        board.place(PieceType::Rook(Rook::config(7, 1, Black)));
+
+        let moves = moves(&board, 6, 0);
+
+        assert_eq!(moves.len(), 8);
+        assert!(moves.iter().all(|m| m.promotion().is_some()));
+        assert!(moves.contains(&Move::promoting((7, 1), (6, 0), PieceKind::Knight)));
+        assert!(moves.contains(&Move::promoting((7, 0), (6, 0), PieceKind::Queen)));
+    }
+
+    #[test]
+    fn test_pawn_checks_king() {
+        use Colour::*;
+
+        let mut board = Board::empty();
+        board.place(PieceType::Pawn(Pawn::config(1, 3, Black)));
+        board.place(PieceType::King(King::config(0, 4, White)));
+
+        let moveset = board[1][3].inner().unwrap().evaluate_moves(&board);
+
+        assert!(moveset.is_checking());
+        assert_eq!(moveset.len(), 4);
+        assert!(moveset.iter().all(|m| m.target() == (0, 3)));
+    }
+}
@@ -5,7 +5,7 @@ use super::*;
 
 pub const POINTS: u8 = 9;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Queen {
     colour: Colour,
     pos: Position,
@@ -13,11 +13,11 @@ pub struct Queen {
 
 impl Queen {
     pub fn config(row: usize, col: usize, colour: Colour) -> Self {
-        let pos = Position {row: row, col: col};
+        let pos = Position {row, col};
 
         Self {
-            colour: colour,
-            pos: pos,
+            colour,
+            pos,
         }
     }
 }
@@ -34,6 +34,55 @@ impl Piece for Queen {
     }
     
     fn evaluate_moves(&self, board: &Board) -> MoveSet {
-        unimplemented!()
+        slide(board, self.pos, self.colour, &ALL_DIRECTIONS)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::MoveMarker;
+
+    #[test]
+    fn test_queen_open_board() {
+        let mut board = Board::empty();
+        board.place(PieceType::Queen(Queen::config(3, 3, Colour::Black)));
+
+        let moves = board[3][3].inner().unwrap().evaluate_moves(&board);
+
+        assert_eq!(moves.len(), 27);
+        assert!(!moves.is_checking());
+    }
+
+    #[test]
+    fn test_queen_boxed_in() {
+        let board = Board::init();
+
+        let moves = board[0][3].inner().unwrap().evaluate_moves(&board);
+
+        assert!(moves.is_empty());
+    }
+
+    #[test]
+    fn test_queen_captures_and_checks() {
+        use Colour::*;
+
+        let mut board = Board::empty();
+        board.place(PieceType::Queen(Queen::config(0, 0, White)));
+        board.place(PieceType::Rook(Rook::config(0, 2, Black)));
+        board.place(PieceType::King(King::config(5, 5, Black)));
+        board.place(PieceType::Pawn(Pawn::config(2, 0, White)));
+
+        let moves = board[0][0].inner().unwrap().evaluate_moves(&board);
+        let targets: Vec<_> = moves.iter().map(|m| m.target()).collect();
+
+        assert!(moves.is_checking());
+        assert!(targets.contains(&(0, 2)));
+        assert!(!targets.contains(&(0, 3)));
+        assert!(!targets.contains(&(2, 0)));
+        assert!(targets.contains(&(1, 0)));
+        assert!(targets.contains(&(4, 4)));
+        assert!(!targets.contains(&(5, 5)));
+        assert_eq!(moves.len(), 7);
+    }
+}
@@ -5,7 +5,7 @@ use super::*;
 
 pub const POINTS: u8 = 5;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Rook {
     colour: Colour,
     pos: Position,
@@ -13,11 +13,11 @@ pub struct Rook {
 
 impl Rook {
     pub fn config(row: usize, col: usize, colour: Colour) -> Self {
-        let pos = Position {row: row, col: col};
+        let pos = Position {row, col};
 
         Self {
-            colour: colour,
-            pos: pos,
+            colour,
+            pos,
         }
     }
 }
@@ -34,6 +34,57 @@ impl Piece for Rook {
     }
     
     fn evaluate_moves(&self, board: &Board) -> MoveSet {
-        unimplemented!()
+        slide(board, self.pos, self.colour, &ORTHOGONALS)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::MoveMarker;
+
+    #[test]
+    fn test_rook_open_board() {
+        let mut board = Board::empty();
+        board.place(PieceType::Rook(Rook::config(3, 3, Colour::White)));
+
+        let moves = board[3][3].inner().unwrap().evaluate_moves(&board);
+
+        assert_eq!(moves.len(), 14);
+        assert!(!moves.is_checking());
+        assert!(moves.iter().all(|m| m.origin() == (3, 3)));
+        assert!(moves.iter().all(|m| m.target().0 == 3 || m.target().1 == 3));
+    }
+
+    #[test]
+    fn test_rook_blocked_and_captures() {
+        use Colour::*;
+
+        let mut board = Board::empty();
+        board.place(PieceType::Rook(Rook::config(0, 0, White)));
+        board.place(PieceType::Pawn(Pawn::config(0, 3, White)));
+        board.place(PieceType::Knight(Knight::config(4, 0, Black)));
+
+        let moves = board[0][0].inner().unwrap().evaluate_moves(&board);
+        let mut targets: Vec<_> = moves.iter().map(|m| m.target()).collect();
+        targets.sort_unstable();
+
+        assert_eq!(targets, vec![(0, 1), (0, 2), (1, 0), (2, 0), (3, 0), (4, 0)]);
+        assert!(!moves.is_checking());
+    }
+
+    #[test]
+    fn test_rook_checks_king() {
+        use Colour::*;
+
+        let mut board = Board::empty();
+        board.place(PieceType::Rook(Rook::config(0, 4, White)));
+        board.place(PieceType::King(King::config(7, 4, Black)));
+
+        let moves = board[0][4].inner().unwrap().evaluate_moves(&board);
+
+        assert!(moves.is_checking());
+        assert!(moves.iter().all(|m| m.target() != (7, 4)));
+        assert_eq!(moves.len(), 13);
+    }
+}
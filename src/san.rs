@@ -0,0 +1,308 @@
+//! Standard Algebraic Notation, as used in PGN and by human players.
+
+use std::fmt;
+use std::error::Error;
+
+use crate::board::{Board, GameState};
+use crate::moves::{Move, MoveMarker};
+use crate::pieces::*;
+
+impl Move {
+    /// Formats the move in Standard Algebraic Notation, such as `Nbd7`,
+    /// `O-O-O`, `e8=Q+` or `Qxf7#`. En passant captures are marked with ` e.p.`.
+    /// 
+    /// The move must be legal on the given board, which is the position
+    /// before the move is played.
+    pub fn to_san(&self, board: &Board) -> String {
+        let (o, t) = (self.origin(), self.target());
+        let piece = board[o.0][o.1];
+        let kind = piece.kind().expect("No piece on the origin square");
+        let target = Position {row: t.0, col: t.1};
+
+        let mut san = String::new();
+        let mut en_passant = false;
+
+        if kind == PieceKind::King && (t.1 as isize - o.1 as isize).abs() == 2 {
+            san.push_str(if t.1 == 6 { "O-O" } else { "O-O-O" });
+        } else if kind == PieceKind::Pawn {
+            if o.1 != t.1 {
+                en_passant = board[t.0][t.1].is_empty();
+                san.push((b'a' + o.1 as u8) as char);
+                san.push('x');
+            }
+            san.push_str(&target.to_string());
+
+            if let Some(promotion) = self.promotion() {
+                san.push('=');
+                san.push(promotion.as_char());
+            }
+        } else {
+            san.push(kind.as_char());
+            san.push_str(&disambiguation(board, self, kind));
+            if !board[t.0][t.1].is_empty() {
+                san.push('x');
+            }
+            san.push_str(&target.to_string());
+        }
+
+        let mut after = board.clone();
+        after.play(*self);
+        match after.eval_gamestate() {
+            GameState::Checkmate(_) => san.push('#'),
+            GameState::Check(_) => san.push('+'),
+            //a draw by rule can still be delivered with check
+            _ if after.is_in_check() => san.push('+'),
+            _ => {}
+        }
+
+        if en_passant {
+            san.push_str(" e.p.");
+        }
+
+        san
+    }
+}
+
+/// Returns the origin file, rank, or square needed to tell the move apart
+/// from other legal moves by the same kind of piece to the same square.
+fn disambiguation(board: &Board, mov: &Move, kind: PieceKind) -> String {
+    let o = mov.origin();
+    let rivals: Vec<(usize, usize)> = board.legal_moves()
+        .iter()
+        .filter(|m| m.target() == mov.target() && m.origin() != o)
+        .filter(|m| board[m.origin().0][m.origin().1].kind() == Some(kind))
+        .map(|m| m.origin())
+        .collect();
+
+    let file = (b'a' + o.1 as u8) as char;
+    let rank = (b'1' + o.0 as u8) as char;
+
+    if rivals.is_empty() {
+        String::new()
+    } else if rivals.iter().all(|r| r.1 != o.1) {
+        file.to_string()
+    } else if rivals.iter().all(|r| r.0 != o.0) {
+        rank.to_string()
+    } else {
+        format!("{}{}", file, rank)
+    }
+}
+
+impl Board {
+    /// Resolves a move written in Standard Algebraic Notation
+    /// against the legal moves of the current position.
+    /// 
+    /// Check and annotation marks are ignored, as are `x` and an
+    /// ` e.p.` suffix. Castling may be written with letter O or zero,
+    /// and the `=` before a promotion piece may be left out.
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        let invalid = || SanError::InvalidSyntax(san.to_string());
+
+        let mut text = san.trim();
+        text = text.strip_suffix("e.p.").unwrap_or(text).trim_end();
+        text = text.trim_end_matches(|c| "+#!?".contains(c));
+
+        let legal = self.legal_moves();
+        let king = self.find_king(self.turn());
+
+        let candidates: Vec<Move> = match text {
+            "O-O" | "0-0" | "O-O-O" | "0-0-0" => {
+                let col = if text.len() == 3 { 6 } else { 2 };
+
+                legal.iter()
+                    .filter(|m| m.origin() == king.as_tuple() && m.target() == (king.row, col))
+                    .copied()
+                    .collect()
+            }
+            _ => {
+                let mut chars: Vec<char> = text.chars().filter(|&c| c != 'x').collect();
+
+                let kind = match chars.first() {
+                    Some(c) if c.is_ascii_uppercase() => {
+                        let kind = PieceKind::from_char(*c).ok_or_else(invalid)?;
+                        chars.remove(0);
+                        kind
+                    }
+                    Some(_) => PieceKind::Pawn,
+                    None => return Err(invalid()),
+                };
+
+                let promotion = match chars.last() {
+                    Some(c) if c.is_ascii_uppercase() => {
+                        let promotion = PieceKind::from_char(*c)
+                            .filter(|k| PieceKind::PROMOTIONS.contains(k))
+                            .ok_or_else(invalid)?;
+                        chars.pop();
+                        if chars.last() == Some(&'=') {
+                            chars.pop();
+                        }
+                        Some(promotion)
+                    }
+                    _ => None,
+                };
+
+                if chars.len() < 2 || chars.len() > 4 {
+                    return Err(invalid())
+                }
+
+                let split = chars.len() - 2;
+                let target: String = chars[split..].iter().collect();
+                let target = Position::from_square(&target).ok_or_else(invalid)?;
+
+                let mut file = None;
+                let mut rank = None;
+                for &c in chars[..split].iter() {
+                    match c {
+                        'a'..='h' if file.is_none() => file = Some(c as usize - 'a' as usize),
+                        '1'..='8' if rank.is_none() => rank = Some(c as usize - '1' as usize),
+                        _ => return Err(invalid()),
+                    }
+                }
+
+                legal.iter()
+                    .filter(|m| m.target() == target.as_tuple() && m.promotion() == promotion)
+                    .filter(|m| self[m.origin().0][m.origin().1].kind() == Some(kind))
+                    .filter(|m| file.is_none_or(|f| m.origin().1 == f))
+                    .filter(|m| rank.is_none_or(|r| m.origin().0 == r))
+                    .copied()
+                    .collect()
+            }
+        };
+
+        match candidates.len() {
+            0 => Err(SanError::IllegalMove(san.to_string())),
+            1 => Ok(candidates[0]),
+            _ => Err(SanError::AmbiguousMove(san.to_string())),
+        }
+    }
+}
+
+/// Describes why a move in Standard Algebraic Notation could not be resolved.
+#[non_exhaustive]
+#[derive(Clone, PartialEq, Debug)]
+pub enum SanError {
+    /// The text is not written in SAN.
+    InvalidSyntax(String),
+    /// No legal move in the position matches the text.
+    IllegalMove(String),
+    /// More than one legal move in the position matches the text.
+    AmbiguousMove(String),
+}
+
+impl Error for SanError {}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidSyntax(s) => {
+                write!(f, "'{}' is not a move in algebraic notation", s)
+            }
+            Self::IllegalMove(s) => {
+                write!(f, "'{}' is not a legal move in this position", s)
+            }
+            Self::AmbiguousMove(s) => {
+                write!(f, "'{}' could refer to more than one move", s)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn san(fen: &str, origin: &str, target: &str) -> String {
+        let board = Board::from_fen(fen).unwrap();
+        let o = Position::from_square(origin).unwrap().as_tuple();
+        let t = Position::from_square(target).unwrap().as_tuple();
+
+        let mov = board.legal_moves()
+            .iter()
+            .find(|m| m.origin() == o && m.target() == t)
+            .copied()
+            .unwrap();
+
+        mov.to_san(&board)
+    }
+
+    #[test]
+    fn test_san_simple() {
+        let start = crate::fen::STARTING_FEN;
+
+        assert_eq!(san(start, "e2", "e4"), "e4");
+        assert_eq!(san(start, "g1", "f3"), "Nf3");
+    }
+
+    #[test]
+    fn test_san_disambiguation() {
+        let knights = "rnbqkb1r/ppp1pppp/5n2/3p4/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1";
+        assert_eq!(san(knights, "b8", "d7"), "Nbd7");
+        assert_eq!(san(knights, "f6", "d7"), "Nfd7");
+        assert_eq!(san(knights, "f6", "e4"), "Ne4");
+
+        let rooks = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+        assert_eq!(san(rooks, "a1", "a3"), "R1a3");
+        assert_eq!(san(rooks, "a5", "a3"), "R5a3");
+        assert_eq!(san(rooks, "a5", "b5"), "Rb5");
+
+        let queens = "4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1";
+        assert_eq!(san(queens, "a1", "b2"), "Qa1b2");
+        assert_eq!(san(queens, "c1", "b2"), "Qcb2");
+        assert_eq!(san(queens, "a3", "b2"), "Q3b2");
+    }
+
+    #[test]
+    fn test_san_special_moves() {
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(san(kiwipete, "e1", "g1"), "O-O");
+        assert_eq!(san(kiwipete, "e1", "c1"), "O-O-O");
+        assert_eq!(san(kiwipete, "d5", "e6"), "dxe6");
+        assert_eq!(san(kiwipete, "f3", "h3"), "Qxh3");
+
+        let en_passant = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3";
+        assert_eq!(san(en_passant, "e5", "d6"), "exd6 e.p.");
+
+        let promotion = "8/4P3/8/8/k7/8/8/4K3 w - - 0 1";
+        assert_eq!(san(promotion, "e7", "e8"), "e8=Q+");
+
+        let mate = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4";
+        assert_eq!(san(mate, "h5", "f7"), "Qxf7#");
+    }
+
+    #[test]
+    fn test_parse_san() {
+        let board = Board::from_fen("rnbqkb1r/ppp1pppp/5n2/3p4/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1").unwrap();
+
+        assert_eq!(board.parse_san("Nbd7"), Ok(Move::construct((6, 3), (7, 1))));
+        assert_eq!(board.parse_san("c5"), Ok(Move::construct((4, 2), (6, 2))));
+        assert_eq!(board.parse_san("Nd7"), Err(SanError::AmbiguousMove("Nd7".to_string())));
+        assert_eq!(board.parse_san("Nc4"), Err(SanError::IllegalMove("Nc4".to_string())));
+        assert_eq!(board.parse_san("O-O"), Err(SanError::IllegalMove("O-O".to_string())));
+        assert_eq!(board.parse_san("Zf3"), Err(SanError::InvalidSyntax("Zf3".to_string())));
+        assert_eq!(board.parse_san("Nx"), Err(SanError::InvalidSyntax("Nx".to_string())));
+
+        let board = Board::from_fen("8/4P3/8/8/k7/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.parse_san("e8=N"), Ok(Move::promoting((7, 4), (6, 4), PieceKind::Knight)));
+        assert_eq!(board.parse_san("e8Q+"), Ok(Move::promoting((7, 4), (6, 4), PieceKind::Queen)));
+        assert_eq!(board.parse_san("e8"), Err(SanError::IllegalMove("e8".to_string())));
+    }
+
+    #[test]
+    fn test_san_round_trip() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+            "4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1",
+        ];
+
+        for fen in fens.iter() {
+            let board = Board::from_fen(fen).unwrap();
+
+            for mov in board.legal_moves() {
+                let san = mov.to_san(&board);
+                assert_eq!(board.parse_san(&san), Ok(mov), "{} in {}", san, fen);
+            }
+        }
+    }
+}
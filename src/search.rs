@@ -0,0 +1,1062 @@
+//! Choosing a move to play in a position.
+//! 
+//! The search is a negamax alpha-beta search driven by iterative deepening:
+//! each iteration searches one ply deeper than the last, starting with the
+//! best line found so far, until a limit is hit or the search is stopped.
+//! How long that may take is left to the `time` module.
+//!
+//! With more than one thread the search is a Lazy SMP search: helper threads
+//! run the same search alongside the main one, sharing only the transposition
+//! table, so each profits from what the others have already found. The main
+//! thread decides when to stop, and the threads then vote on the move to play.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crate::board::Board;
+use crate::eval;
+use crate::moves::{Move, MoveMarker, MoveSet};
+use crate::ordering::{self, Heuristics, MovePicker};
+use crate::pieces::PieceKind;
+use crate::time::{SystemClock, TimeManager};
+use crate::tt::{Bound, Entry, TranspositionTable};
+
+/// Score given to delivering checkmate, in centipawns.
+/// Mates further away score less, so the search prefers the quickest.
+pub const MATE_SCORE: i32 = 30000;
+
+/// The deepest the search will ever go, in plies.
+pub const MAX_DEPTH: u32 = 64;
+
+/// Larger than any score the search can return.
+const INFINITY: i32 = MATE_SCORE + 1;
+
+/// Slack given to captures in quiescence search before they are pruned
+/// as unable to raise the score, to allow for positional gains.
+const DELTA_MARGIN: i32 = 200;
+
+/// Size of the table made for a search not given one, in megabytes.
+const SCRATCH_TT_MB: usize = 1;
+
+/// Scores beyond this are mates, which pruning must not cut short.
+const MATE_BOUND: i32 = MATE_SCORE - MAX_DEPTH as i32;
+
+/// Half the width of the first aspiration window around the last score.
+const ASPIRATION_WINDOW: i32 = 50;
+
+/// How far the static evaluation may fall short of alpha, by remaining
+/// depth, before quiet moves are assumed unable to make up the difference.
+const FUTILITY_MARGINS: [i32; 4] = [0, 150, 300, 500];
+
+/// How far the static evaluation must clear beta, per ply of remaining
+/// depth, for a node to be cut off without searching it.
+const REVERSE_FUTILITY_MARGIN: i32 = 100;
+
+/// Which pruning, reduction and extension techniques the search uses.
+/// 
+/// Every technique is on by default; each can be turned off on its own
+/// to measure what it is worth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Let the opponent move twice, and prune if the position still holds.
+    /// Never used in pawn endings, where having to move can be a disadvantage.
+    pub null_move: bool,
+    /// Search quiet moves that come late in the move order less deeply.
+    pub late_move_reductions: bool,
+    /// Skip quiet moves near the horizon when the position is too far
+    /// below alpha for them to matter.
+    pub futility: bool,
+    /// Cut off nodes near the horizon whose static evaluation is far above beta.
+    pub reverse_futility: bool,
+    /// Search each iteration in a narrow window around the last score,
+    /// widening it only if the score falls outside.
+    pub aspiration: bool,
+    /// Search one ply deeper when in check.
+    pub check_extensions: bool,
+}
+
+impl SearchOptions {
+    /// Plain alpha-beta, with every technique turned off.
+    pub fn none() -> Self {
+        SearchOptions {
+            null_move: false,
+            late_move_reductions: false,
+            futility: false,
+            reverse_futility: false,
+            aspiration: false,
+            check_extensions: false,
+        }
+    }
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            null_move: true,
+            late_move_reductions: true,
+            futility: true,
+            reverse_futility: true,
+            aspiration: true,
+            check_extensions: true,
+        }
+    }
+}
+
+/// Bounds on how long and how deep a search may run.
+/// 
+/// Every limit is optional; a search with none set runs until stopped.
+#[derive(Clone, Debug, Default)]
+pub struct SearchLimits {
+    /// Maximum depth in plies.
+    pub depth: Option<u32>,
+    /// Exact time to spend on the move.
+    pub movetime: Option<Duration>,
+    /// Time left on white's clock.
+    pub wtime: Option<Duration>,
+    /// Time left on black's clock.
+    pub btime: Option<Duration>,
+    /// White's increment per move.
+    pub winc: Option<Duration>,
+    /// Black's increment per move.
+    pub binc: Option<Duration>,
+    /// Moves left until the next time control.
+    pub movestogo: Option<u32>,
+    /// Time kept back on the clock for communication with the GUI,
+    /// or `time::DEFAULT_MOVE_OVERHEAD` if not given.
+    pub move_overhead: Option<Duration>,
+    /// Search until stopped, regardless of the other limits.
+    pub infinite: bool,
+    /// Raised from another thread to stop the search early.
+    pub stop: Arc<AtomicBool>,
+    /// Results of earlier searches, kept between moves.
+    /// Without one the search makes a small table of its own.
+    pub tt: Option<Arc<TranspositionTable>>,
+    /// The techniques the search may use.
+    pub options: SearchOptions,
+    /// How many of the best lines to find, each with its own score.
+    /// With 0 or 1 only the best line is searched for.
+    pub multipv: usize,
+    /// How many threads to search with. With 0 or 1 the search runs
+    /// on the calling thread alone, and is repeatable from run to run.
+    pub threads: usize,
+}
+
+/// The outcome of a search.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    /// The move to play, or None if the side to move has no legal moves.
+    pub best_move: Option<Move>,
+    /// The score of the position in centipawns, from the side to move's view.
+    pub score: i32,
+    /// The expected line of play, starting with the best move.
+    pub pv: Vec<Move>,
+    /// The number of positions visited.
+    pub nodes: u64,
+    /// The depth of the last completed iteration, in plies.
+    pub depth: u32,
+    /// The best lines found, best first, as many as were asked for
+    /// and there are legal moves. The first is the same as `score` and `pv`.
+    pub lines: Vec<PvLine>,
+}
+
+/// One of the lines found by a search that looks for several.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PvLine {
+    /// The score of the line in centipawns, from the side to move's view.
+    pub score: i32,
+    /// The expected line of play, starting with the move it is the line for.
+    pub pv: Vec<Move>,
+}
+
+/// Searches the position for the best move within the given limits.
+pub fn search(board: &Board, limits: &SearchLimits) -> SearchResult {
+    search_with(board, limits, |_| {})
+}
+
+/// Searches like `search`, calling `report` with the result
+/// of every completed iteration as it finishes.
+/// 
+/// Only the main thread reports, but the node counts include every thread.
+pub fn search_with<F>(board: &Board, limits: &SearchLimits, mut report: F) -> SearchResult
+where F: FnMut(&SearchResult) {
+    let tt = limits.tt.clone()
+        .unwrap_or_else(|| Arc::new(TranspositionTable::new(SCRATCH_TT_MB)));
+    tt.new_search();
+
+    if board.legal_moves().is_empty() {
+        return SearchResult {
+            best_move: None,
+            score: if board.checkers() != 0 { -MATE_SCORE } else { 0 },
+            pv: Vec::new(),
+            nodes: 0,
+            depth: 0,
+            lines: Vec::new(),
+        }
+    }
+
+    //helpers have no clock of their own, and run until the main thread is done
+    let helper_nodes = Arc::new(AtomicU64::new(0));
+    let helper_limits = SearchLimits {
+        movetime: None,
+        wtime: None,
+        btime: None,
+        infinite: true,
+        multipv: 1,
+        stop: Arc::new(AtomicBool::new(false)),
+        ..limits.clone()
+    };
+
+    thread::scope(|scope| {
+        let helpers: Vec<_> = (1..limits.threads.max(1)).map(|id| {
+            let (helper_limits, helper_nodes, tt) = (&helper_limits, helper_nodes.clone(), tt.clone());
+
+            scope.spawn(move || {
+                let mut helper = Searcher::new(board, helper_limits, tt);
+                helper.shared_nodes = Some(helper_nodes);
+
+                //half the helpers start a ply deeper, so the threads
+                //spread out over different depths rather than racing in step
+                helper.iterate(helper_limits, 1 + id as u32 % 2, |_| {})
+            })
+        }).collect();
+
+        let mut main = Searcher::new(board, limits, tt.clone());
+        let result = main.iterate(limits, 1, |result| {
+            let result = SearchResult {
+                nodes: result.nodes + helper_nodes.load(Ordering::Relaxed),
+                ..result.clone()
+            };
+            report(&result)
+        });
+
+        helper_limits.stop.store(true, Ordering::SeqCst);
+        let mut results = vec![result];
+        results.extend(helpers.into_iter().map(|h| h.join().expect("search thread panicked")));
+
+        //helpers only look for the best line, so only the main thread has them all
+        let nodes = results.iter().map(|r| r.nodes).sum();
+        let chosen = if limits.multipv > 1 { &results[0] } else { vote(&results) };
+        SearchResult {nodes, ..chosen.clone()}
+    })
+}
+
+/// Picks the result to play from those of every thread, the main thread's first.
+/// 
+/// Each thread votes for its best move, with a weight that grows with how
+/// deep it searched and how well the move scored, so that a move several
+/// threads agree on beats one thread's outlier. A move only one helper
+/// found never overrules the main thread, however deep that helper got.
+/// A shorter forced mate beats everything, and ties go to the earlier thread.
+fn vote(results: &[SearchResult]) -> &SearchResult {
+    let worst = results.iter().map(|r| r.score).min().unwrap_or(0);
+    // each move with its total weight and how many threads chose it
+    let mut votes: Vec<(Move, i64, usize)> = Vec::new();
+
+    for result in results {
+        if let Some(mov) = result.best_move {
+            let weight = (result.score - worst + 20) as i64 * result.depth as i64;
+            match votes.iter_mut().find(|(m, _, _)| *m == mov) {
+                Some((_, total, voters)) => {
+                    *total += weight;
+                    *voters += 1;
+                }
+                None => votes.push((mov, weight, 1)),
+            }
+        }
+    }
+    let votes_for = |result: &SearchResult| votes.iter()
+        .find(|(m, _, _)| Some(*m) == result.best_move)
+        .map_or((0, 0), |(_, total, voters)| (*total, *voters));
+
+    let mut best = &results[0];
+    for result in &results[1..] {
+        let better = if best.score >= MATE_BOUND || result.score >= MATE_BOUND {
+            result.score > best.score
+        } else {
+            let (total, voters) = votes_for(result);
+            voters > 1 && total > votes_for(best).0
+        };
+        if better && result.best_move.is_some() {
+            best = result;
+        }
+    }
+
+    best
+}
+
+struct Searcher {
+    board: Board,
+    stop: Arc<AtomicBool>,
+    tt: Arc<TranspositionTable>,
+    time: TimeManager,
+    nodes: u64,
+    can_stop: bool,
+    stopped: bool,
+    // the principal variation found below each ply
+    pv: Vec<Vec<Move>>,
+    // killers, countermoves and history gathered over the search
+    heuristics: Heuristics,
+    options: SearchOptions,
+    // set just before searching a null move, so the reply cannot pass too
+    after_null: bool,
+    // the principal variation of the last iteration, and whether the
+    // current node lies along it
+    prev_pv: Vec<Move>,
+    following: bool,
+    // where a helper thread adds its node count as it goes, for the main thread to report
+    shared_nodes: Option<Arc<AtomicU64>>,
+    // root moves already given lines of their own in this iteration
+    excluded: Vec<Move>,
+}
+
+impl Searcher {
+    fn new(board: &Board, limits: &SearchLimits, tt: Arc<TranspositionTable>) -> Self {
+        Searcher {
+            board: board.clone(),
+            stop: limits.stop.clone(),
+            tt,
+            time: TimeManager::new(limits, board.turn(), Box::new(SystemClock::start())),
+            nodes: 0,
+            can_stop: false,
+            stopped: false,
+            pv: vec![Vec::new(); MAX_DEPTH as usize + 1],
+            heuristics: Heuristics::new(),
+            options: limits.options,
+            after_null: false,
+            prev_pv: Vec::new(),
+            following: false,
+            shared_nodes: None,
+            excluded: Vec::new(),
+        }
+    }
+
+    /// Runs iterative deepening from the given depth until a limit is hit or
+    /// the search is stopped, returning the result of the last iteration
+    /// that completed.
+    fn iterate<F>(&mut self, limits: &SearchLimits, start: u32, mut report: F) -> SearchResult
+    where F: FnMut(&SearchResult) {
+        let mut result = SearchResult {
+            best_move: None,
+            score: 0,
+            pv: Vec::new(),
+            nodes: 0,
+            depth: 0,
+            lines: Vec::new(),
+        };
+        let max_depth = limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+        let start = start.min(max_depth);
+        let wanted = limits.multipv.clamp(1, self.board.legal_moves().len());
+
+        'deepening: for depth in start..=max_depth {
+            //the first iteration always completes, so there is always a move to play
+            self.can_stop = depth > start;
+
+            //each line after the first is the best line that avoids
+            //the first moves of those found before it
+            let mut lines: Vec<PvLine> = Vec::with_capacity(wanted);
+            self.excluded.clear();
+            for k in 0..wanted {
+                let last = result.lines.get(k).or_else(|| result.lines.last());
+                self.prev_pv = last.map(|l| l.pv.clone()).unwrap_or_default();
+                self.following = true;
+
+                let score = self.aspirate(depth, last.map_or(0, |l| l.score));
+                if self.stopped {
+                    break 'deepening
+                }
+
+                let pv = self.pv[0].clone();
+                self.excluded.push(pv[0]);
+                lines.push(PvLine {score, pv});
+            }
+
+            //what the later searches add to the table can lift a later line above an earlier one
+            lines.sort_by_key(|line| -line.score);
+            let score = lines[0].score;
+
+            result.score = score;
+            result.pv = lines[0].pv.clone();
+            result.best_move = result.pv.first().copied();
+            result.depth = depth;
+            result.nodes = self.nodes;
+            result.lines = lines;
+            report(&result);
+
+            //a forced mate will not get any better by searching deeper
+            if score.abs() >= MATE_SCORE - depth as i32 && !limits.infinite {
+                break
+            }
+            if !self.time.iteration_done(result.best_move, score) {
+                break
+            }
+        }
+
+        result.nodes = self.nodes;
+        result
+    }
+
+    fn in_check(&self) -> bool {
+        self.board.checkers() != 0
+    }
+
+    /// Checks the stop flag, and the clock every so often.
+    fn should_stop(&mut self) -> bool {
+        if self.nodes.is_multiple_of(1024) {
+            if let Some(shared) = &self.shared_nodes {
+                shared.fetch_add(1024, Ordering::Relaxed);
+            }
+        }
+
+        if self.can_stop && !self.stopped {
+            let out_of_time = self.nodes.is_multiple_of(1024)
+                && self.time.out_of_time();
+
+            self.stopped = out_of_time || self.stop.load(Ordering::Relaxed);
+        }
+
+        self.stopped
+    }
+
+    /// Searches the root to the given depth, first in a narrow window around
+    /// the last iteration's score and widening it on whichever side the
+    /// score falls outside, until the score lands inside it.
+    fn aspirate(&mut self, depth: u32, last: i32) -> i32 {
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = if self.options.aspiration && depth >= 4 && last.abs() < MATE_BOUND {
+            (last - delta, last + delta)
+        } else {
+            (-INFINITY, INFINITY)
+        };
+
+        loop {
+            let score = self.negamax(depth, 0, alpha, beta);
+            if self.stopped {
+                return score
+            }
+
+            if score <= alpha {
+                alpha = (alpha - delta).max(-INFINITY);
+            } else if score >= beta {
+                beta = (beta + delta).min(INFINITY);
+            } else {
+                return score
+            }
+
+            delta *= 2;
+            self.following = true;
+        }
+    }
+
+    /// Searches to the given depth, returning the score for the side to move.
+    /// Scores at or below `alpha` or at or above `beta` are only bounds.
+    fn negamax(&mut self, depth: u32, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.pv[ply].clear();
+        self.nodes += 1;
+
+        let after_null = std::mem::replace(&mut self.after_null, false);
+
+        if self.should_stop() {
+            return 0
+        }
+
+        let moves = self.board.legal_moves();
+        let in_check = self.in_check();
+        if moves.is_empty() {
+            return if in_check { -MATE_SCORE + ply as i32 } else { 0 }
+        }
+        if ply > 0 && (self.board.halfmove_clock() >= 100 || self.board.is_repetition()) {
+            return 0
+        }
+
+        //a check has to be answered, so look past it rather than stopping short
+        let depth = if in_check && self.options.check_extensions { depth + 1 } else { depth };
+
+        if depth == 0 || ply >= MAX_DEPTH as usize {
+            return self.quiesce(ply, alpha, beta)
+        }
+
+        let hash = self.board.hash();
+        let entry = self.tt.probe(hash, ply);
+
+        //a deep enough earlier search may already settle this node
+        if let Some(entry) = entry.filter(|e| ply > 0 && e.depth >= depth) {
+            let settled = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.score >= beta,
+                Bound::Upper => entry.score <= alpha,
+            };
+            if settled {
+                return entry.score
+            }
+        }
+
+        //the shortcuts below only suit nodes that are expected to fail one way,
+        //not those on the principal variation whose exact score matters
+        let pv_node = beta - alpha > 1;
+        let static_eval = if in_check { -INFINITY } else { eval::evaluate(&self.board) };
+        let mating = beta.abs() >= MATE_BOUND;
+
+        if !pv_node && !in_check && !mating {
+            if self.options.reverse_futility && depth <= 6
+                && static_eval - REVERSE_FUTILITY_MARGIN * depth as i32 >= beta {
+                return static_eval
+            }
+
+            if self.options.null_move && !after_null && depth >= 3 && static_eval >= beta
+                && self.has_pieces() {
+                if let Some(score) = self.null_move(depth, ply, beta) {
+                    return score
+                }
+            }
+        }
+
+        //search the best line from the last iteration first, else the stored best move
+        let hint = self.prev_pv.get(ply).copied().filter(|_| self.following);
+        let hash_move = hint.or_else(|| entry.and_then(|e| e.best_move));
+        let mut picker = MovePicker::new(&self.board, moves, hash_move, &self.heuristics, ply);
+
+        let futile = self.options.futility && !pv_node && !in_check && !mating
+            && (depth as usize) < FUTILITY_MARGINS.len()
+            && static_eval + FUTILITY_MARGINS[depth as usize] <= alpha;
+
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        let mut quiets_tried = Vec::new();
+        let mut searched = 0;
+
+        while let Some(mov) = picker.next(&self.board, &self.heuristics) {
+            if ply == 0 && self.excluded.contains(&mov) {
+                continue
+            }
+            self.following = searched == 0 && hint == Some(mov);
+            let quiet = !ordering::is_tactical(&self.board, &mov);
+
+            self.board.play(mov);
+            let gives_check = self.in_check();
+
+            //quiet moves cannot lift a hopeless position back up to alpha
+            if futile && quiet && !gives_check && searched > 0 {
+                self.board.unmake_move();
+                continue
+            }
+
+            //late quiet moves are rarely best, so give them a shallower look first,
+            //except at the root where the move to play is chosen
+            let reduction = if self.options.late_move_reductions && ply > 0 && depth >= 3
+                && searched >= 3 && quiet && !in_check && !gives_check {
+                (1 + (searched >= 6 && depth >= 6) as u32 + !pv_node as u32).min(depth - 2)
+            } else {
+                0
+            };
+
+            let mut score = if reduction > 0 {
+                -self.negamax(depth - 1 - reduction, ply + 1, -alpha - 1, -alpha)
+            } else {
+                alpha + 1
+            };
+            if score > alpha {
+                score = -self.negamax(depth - 1, ply + 1, -beta, -alpha);
+            }
+            self.board.unmake_move();
+            searched += 1;
+
+            if self.stopped {
+                return 0
+            }
+
+            if score > best {
+                best = score;
+                best_move = Some(mov);
+            }
+            if score > alpha {
+                alpha = score;
+
+                let (head, tail) = self.pv.split_at_mut(ply + 1);
+                head[ply].clear();
+                head[ply].push(mov);
+                head[ply].extend_from_slice(&tail[0]);
+            }
+            if alpha >= beta {
+                //quiet moves that refute a position tend to refute its neighbours too
+                if quiet {
+                    self.heuristics.reward(&self.board, mov, ply, depth, &quiets_tried);
+                }
+                break
+            }
+            if quiet {
+                quiets_tried.push(mov);
+            }
+        }
+
+        let bound = if best >= beta {
+            Bound::Lower
+        } else if best > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        //with moves left out, the root's score is not the position's own
+        if ply > 0 || self.excluded.is_empty() {
+            self.tt.store(hash, ply, Entry {best_move, score: best, depth, bound});
+        }
+
+        best
+    }
+
+    /// Lets the opponent move again and searches the result shallowly.
+    /// If the side to move still reaches beta after giving away a whole move,
+    /// a real move would surely do so too, and the node can be cut off.
+    fn null_move(&mut self, depth: u32, ply: usize, beta: i32) -> Option<i32> {
+        let reduction = 2 + depth / 4;
+
+        let undo = self.board.play_null();
+        self.following = false;
+        self.after_null = true;
+        let score = -self.negamax(depth.saturating_sub(1 + reduction), ply + 1, -beta, -beta + 1);
+        self.board.unmake_null(undo);
+
+        //a mate found after passing is not to be trusted
+        if !self.stopped && score >= beta {
+            Some(if score >= MATE_BOUND { beta } else { score })
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether the side to move has anything besides pawns and its king.
+    /// In pawn endings passing can be better than any move, so null moves are off.
+    fn has_pieces(&self) -> bool {
+        let pawns_and_kings = self.board.pieces_of_kind(PieceKind::Pawn)
+            | self.board.pieces_of_kind(PieceKind::King);
+
+        self.board.pieces_of_colour(self.board.turn()) & !pawns_and_kings != 0
+    }
+
+    /// Searches captures and promotions until the position is quiet,
+    /// so that the static evaluation is never taken in the middle of an exchange.
+    /// 
+    /// The side to move may "stand pat" on the static evaluation instead
+    /// of capturing, unless it is in check: then every way out of check is
+    /// searched, so that mates are not missed at the horizon.
+    fn quiesce(&mut self, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+
+        if self.should_stop() {
+            return 0
+        }
+
+        let moves = self.board.legal_moves();
+        let in_check = self.in_check();
+        if moves.is_empty() {
+            return if in_check { -MATE_SCORE + ply as i32 } else { 0 }
+        }
+        if ply >= MAX_DEPTH as usize {
+            return eval::evaluate(&self.board)
+        }
+
+        let stand_pat = if in_check { -INFINITY } else { eval::evaluate(&self.board) };
+        if stand_pat >= beta {
+            return stand_pat
+        }
+        if stand_pat > alpha {
+            alpha = stand_pat;
+        }
+
+        let mut captures = MoveSet::from(moves
+            .into_iter()
+            .filter(|mov| in_check || ordering::is_tactical(&self.board, mov)));
+        for i in 0..captures.len() {
+            let score = ordering::mvv_lva(&self.board, &captures[i]);
+            captures.set_score(i, score);
+        }
+
+        let mut best = stand_pat;
+        let mut index = 0;
+
+        while let Some((mov, _)) = captures.pick_best(index) {
+            index += 1;
+
+            let gain = self.board.captured(&mov).map_or(0, eval::piece_value)
+                + mov.promotion().map_or(0, |kind| {
+                    eval::piece_value(kind) - eval::piece_value(PieceKind::Pawn)
+                });
+
+            //delta pruning: skip captures that cannot lift the score back to alpha
+            if !in_check && stand_pat + gain + DELTA_MARGIN <= alpha {
+                continue
+            }
+            //and captures that lose material outright
+            if !in_check && self.board.see(&mov) < 0 {
+                continue
+            }
+
+            self.board.play(mov);
+            let score = -self.quiesce(ply + 1, -beta, -alpha);
+            self.board.unmake_move();
+
+            if self.stopped {
+                return 0
+            }
+
+            if score > best {
+                best = score;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                break
+            }
+        }
+
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn limits(depth: u32) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        }
+    }
+
+    #[test]
+    fn test_search_takes_free_piece() {
+        let board = Board::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+        let result = search(&board, &limits(2));
+
+        assert_eq!(result.best_move, Some("d1d5".parse().unwrap()));
+        //a rook up, give or take where the pieces stand
+        assert!((400..600).contains(&result.score), "{}", result.score);
+        assert_eq!(result.depth, 2);
+    }
+
+    #[test]
+    fn test_search_sees_past_horizon() {
+        //at depth 1 the pawn looks free, but the recapture is found in quiescence
+        let board = Board::from_fen("4k3/8/2p5/3p4/8/8/8/3RK3 w - - 0 1").unwrap();
+        let result = search(&board, &limits(1));
+
+        assert_ne!(result.best_move, Some("d1d5".parse().unwrap()));
+        assert!(result.score > 0, "{}", result.score);
+    }
+
+    #[test]
+    fn test_search_avoids_defended_piece() {
+        //a defended queen is still worth a rook
+        let board = Board::from_fen("4k3/8/5n2/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+        let result = search(&board, &limits(3));
+
+        assert_eq!(result.best_move, Some("d1d5".parse().unwrap()));
+
+        //but a defended rook is not worth the queen
+        let board = Board::from_fen("4k3/8/5n2/3r4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let result = search(&board, &limits(3));
+
+        assert_ne!(result.best_move, Some("d1d5".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_search_finds_mate_in_one() {
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = search(&board, &limits(4));
+
+        assert_eq!(result.best_move, Some("a1a8".parse().unwrap()));
+        assert_eq!(result.score, MATE_SCORE - 1);
+        assert_eq!(result.depth, 1);
+    }
+
+    #[test]
+    fn test_search_finds_mate_in_two() {
+        let board = Board::from_fen("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1").unwrap();
+        let result = search(&board, &limits(3));
+
+        assert_eq!(result.score, MATE_SCORE - 3);
+        assert_eq!(result.pv.len(), 3);
+
+        //the principal variation must be playable
+        let mut board = board;
+        for mov in result.pv {
+            board.move_piece(mov).unwrap();
+        }
+        assert!(board.eval_gamestate().is_over());
+    }
+
+    #[test]
+    fn test_search_no_moves() {
+        let board = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        let result = search(&board, &limits(3));
+
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, 0);
+        assert!(result.pv.is_empty());
+
+        let board = Board::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert_eq!(search(&board, &limits(3)).score, -MATE_SCORE);
+    }
+
+    #[test]
+    fn test_search_reports_each_depth() {
+        let board = Board::init();
+        let mut depths = Vec::new();
+
+        let result = search_with(&board, &limits(3), |r| depths.push(r.depth));
+
+        assert_eq!(depths, vec![1, 2, 3]);
+        assert!(result.best_move.is_some());
+        assert!(result.nodes > 20);
+    }
+
+    #[test]
+    fn test_search_stops_on_flag() {
+        let board = Board::init();
+        let limits = SearchLimits::default();
+        limits.stop.store(true, Ordering::SeqCst);
+
+        //even a search stopped before it starts has a move to play
+        let result = search(&board, &limits);
+
+        assert_eq!(result.depth, 1);
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn test_search_stops_from_another_thread() {
+        let board = Board::init();
+        let limits = SearchLimits::default();
+        let stop = limits.stop.clone();
+
+        let handle = std::thread::spawn(move || search(&board, &limits));
+        std::thread::sleep(Duration::from_millis(50));
+        stop.store(true, Ordering::SeqCst);
+
+        let result = handle.join().unwrap();
+        assert!(result.best_move.is_some());
+        assert!(result.depth < MAX_DEPTH);
+    }
+
+    #[test]
+    fn test_search_respects_movetime() {
+        let board = Board::init();
+        let limits = SearchLimits {
+            movetime: Some(Duration::from_millis(100)),
+            ..SearchLimits::default()
+        };
+
+        let start = Instant::now();
+        let result = search(&board, &limits);
+
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn test_search_respects_clock() {
+        //a second left in sudden death leaves little to think with
+        let board = Board::init();
+        let limits = SearchLimits {
+            wtime: Some(Duration::from_secs(1)),
+            btime: Some(Duration::from_secs(1)),
+            ..SearchLimits::default()
+        };
+
+        let start = Instant::now();
+        let result = search(&board, &limits);
+
+        assert!(start.elapsed() < Duration::from_millis(800), "{:?}", start.elapsed());
+        assert!(result.best_move.is_some());
+    }
+
+    /// Every option turned off except the one named.
+    fn only(option: &str) -> SearchOptions {
+        let mut options = SearchOptions::none();
+        match option {
+            "null_move" => options.null_move = true,
+            "late_move_reductions" => options.late_move_reductions = true,
+            "futility" => options.futility = true,
+            "reverse_futility" => options.reverse_futility = true,
+            "aspiration" => options.aspiration = true,
+            "check_extensions" => options.check_extensions = true,
+            _ => unreachable!(),
+        }
+        options
+    }
+
+    const OPTIONS: [&str; 6] = [
+        "null_move", "late_move_reductions", "futility",
+        "reverse_futility", "aspiration", "check_extensions",
+    ];
+
+    #[test]
+    fn test_search_options_keep_tactics() {
+        let mate = Board::from_fen("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1").unwrap();
+        let defended = Board::from_fen("4k3/8/5n2/3r4/8/8/8/3QK3 w - - 0 1").unwrap();
+
+        let mut all = vec![SearchOptions::default(), SearchOptions::none()];
+        all.extend(OPTIONS.iter().map(|o| only(o)));
+        for options in all {
+            let result = search(&mate, &SearchLimits {options, ..limits(5)});
+            assert_eq!(result.score, MATE_SCORE - 3, "{:?}", options);
+
+            let result = search(&defended, &SearchLimits {options, ..limits(5)});
+            assert_ne!(result.best_move, Some("d1d5".parse().unwrap()), "{:?}", options);
+        }
+    }
+
+    #[test]
+    fn test_search_options_prune() {
+        let board = Board::from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4"
+        ).unwrap();
+
+        let plain = search(&board, &SearchLimits {options: SearchOptions::none(), ..limits(5)});
+        let pruned = search(&board, &limits(5));
+
+        assert!(pruned.nodes < plain.nodes, "{} >= {}", pruned.nodes, plain.nodes);
+    }
+
+    #[test]
+    fn test_search_null_move_zugzwang() {
+        //whoever has to move here loses the opposition, so passing would be a
+        //move better than any legal one and null moves must not be tried
+        let board = Board::from_fen("8/8/3k4/1p1p4/1P1P4/3K4/8/8 w - - 0 1").unwrap();
+        let with = search(&board, &SearchLimits {options: only("null_move"), ..limits(6)});
+        let without = search(&board, &SearchLimits {options: SearchOptions::none(), ..limits(6)});
+
+        assert_eq!(with.score, without.score);
+        assert_eq!(with.best_move, without.best_move);
+    }
+
+    #[test]
+    fn test_search_single_thread_repeatable() {
+        let board = Board::from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4"
+        ).unwrap();
+
+        let first = search(&board, &limits(5));
+        let second = search(&board, &SearchLimits {threads: 1, ..limits(5)});
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_search_threads() {
+        let mate = Board::from_fen("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1").unwrap();
+        let result = search(&mate, &SearchLimits {threads: 4, ..limits(5)});
+        assert_eq!(result.score, MATE_SCORE - 3);
+
+        let defended = Board::from_fen("4k3/8/5n2/3r4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let result = search(&defended, &SearchLimits {threads: 4, ..limits(5)});
+        assert_ne!(result.best_move, Some("d1d5".parse().unwrap()));
+
+        //the helpers' nodes are counted as well as the main thread's
+        let board = Board::init();
+        let mut reported = Vec::new();
+        let result = search_with(&board, &SearchLimits {threads: 3, ..limits(6)}, |r| reported.push(r.nodes));
+        assert!(result.nodes >= *reported.last().unwrap());
+        assert_eq!(result.depth, 6);
+    }
+
+    #[test]
+    fn test_search_threads_stop() {
+        let board = Board::init();
+        let limits = SearchLimits {
+            movetime: Some(Duration::from_millis(100)),
+            threads: 4,
+            ..SearchLimits::default()
+        };
+
+        let start = Instant::now();
+        let result = search(&board, &limits);
+
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn test_vote() {
+        let result = |mov: &str, score, depth| SearchResult {
+            best_move: Some(mov.parse().unwrap()),
+            score,
+            pv: vec![mov.parse().unwrap()],
+            nodes: 0,
+            depth,
+            lines: vec![PvLine {score, pv: vec![mov.parse().unwrap()]}],
+        };
+
+        //two helpers agreeing outvote the main thread
+        let results = [result("e2e4", 30, 10), result("d2d4", 25, 10), result("d2d4", 25, 10)];
+        assert_eq!(vote(&results), &results[1]);
+
+        //but a lone helper does not, however deep it got
+        let results = [result("e2e4", 30, 10), result("d2d4", 30, 20)];
+        assert_eq!(vote(&results), &results[0]);
+
+        //a mate beats any vote, and a quicker mate beats a slower one
+        let results = [
+            result("e2e4", 30, 10), result("e2e4", 30, 10),
+            result("d2d4", MATE_SCORE - 7, 8), result("c2c4", MATE_SCORE - 5, 6),
+        ];
+        assert_eq!(vote(&results), &results[3]);
+    }
+
+    #[test]
+    fn test_search_multipv() {
+        //taking the queen is best, and taking the knight walks into a fork
+        let board = Board::from_fen("4k3/8/8/3q4/5n2/8/8/3RKR2 w - - 0 1").unwrap();
+        let mut reported = Vec::new();
+        let result = search_with(&board, &SearchLimits {multipv: 3, ..limits(4)}, |r| reported.push(r.lines.len()));
+
+        assert_eq!(reported, vec![3; 4]);
+        assert_eq!(result.lines.len(), 3);
+        assert_eq!(result.lines[0].pv, result.pv);
+        assert_eq!(result.lines[0].score, result.score);
+        assert_eq!(result.lines[0].pv[0], "d1d5".parse().unwrap());
+        assert!(result.lines.iter().all(|l| l.pv[0] != "f1f4".parse().unwrap()));
+
+        //ranked best first, and each line for a different move
+        assert!(result.lines.windows(2).all(|w| w[0].score >= w[1].score));
+        let mut firsts: Vec<Move> = result.lines.iter().map(|l| l.pv[0]).collect();
+        firsts.dedup();
+        assert_eq!(firsts.len(), 3);
+
+        //the best line is the one a single-line search finds
+        let single = search(&board, &limits(4));
+        assert_eq!(single.best_move, result.best_move);
+        assert_eq!(single.lines.len(), 1);
+    }
+
+    #[test]
+    fn test_search_multipv_few_moves() {
+        let board = Board::from_fen("7k/8/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let result = search(&board, &SearchLimits {multipv: 10, ..limits(2)});
+
+        assert_eq!(result.lines.len(), 3);
+    }
+
+    #[test]
+    fn test_quiesce_searches_evasions() {
+        //black is a rook and bishop up, but its only way out of check is mated
+        let board = Board::from_fen("3R3k/6pp/8/2b5/8/7K/r7/8 b - - 0 1").unwrap();
+        let mut searcher = Searcher::new(&board, &limits(1), Arc::new(TranspositionTable::new(1)));
+
+        assert_eq!(searcher.quiesce(0, -INFINITY, INFINITY), -MATE_SCORE + 2);
+    }
+
+    #[test]
+    fn test_quiesce_stalemate() {
+        //white is a queen up, but black has no moves and is not in check
+        let board = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        let mut searcher = Searcher::new(&board, &limits(1), Arc::new(TranspositionTable::new(1)));
+
+        assert_eq!(searcher.quiesce(0, -INFINITY, INFINITY), 0);
+    }
+}
@@ -0,0 +1,151 @@
+//! Static exchange evaluation: working out what a capture wins or loses
+//! once every piece bearing on the square has joined in.
+
+use crate::bitboard::{self, Bitboard};
+use crate::board::Board;
+use crate::eval::piece_value;
+use crate::moves::{Move, MoveMarker};
+use crate::pieces::*;
+
+/// Stands in for the king's value, so it is always the last to recapture.
+const KING_VALUE: i32 = 10000;
+
+fn value(kind: PieceKind) -> i32 {
+    match kind {
+        PieceKind::King => KING_VALUE,
+        kind => piece_value(kind),
+    }
+}
+
+impl Board {
+    /// Returns the material the side to move can expect to gain, in centipawns,
+    /// by playing the move and then trading off on its target square.
+    /// 
+    /// Each side recaptures with its least valuable piece, and either side
+    /// may stop trading when it is ahead. Pieces behind the exchanging ones
+    /// join in as the way opens up. Pins and checks elsewhere are ignored.
+    /// A negative result means the move loses material.
+    pub fn see(&self, mov: &Move) -> i32 {
+        let (o, t) = (mov.origin(), mov.target());
+        let target = bitboard::square(t.0, t.1);
+
+        let mover = match self[o.0][o.1].kind() {
+            Some(kind) => kind,
+            None => return 0,
+        };
+
+        let mut gain = Vec::with_capacity(32);
+        gain.push(self.captured(mov).map_or(0, piece_value));
+
+        //the piece on the square is the next one up for capture
+        let mut on_square = value(mover);
+        if let Some(kind) = mov.promotion() {
+            gain[0] += piece_value(kind) - piece_value(PieceKind::Pawn);
+            on_square = piece_value(kind);
+        }
+
+        let mut occupied = self.occupied() & !(1 << bitboard::square(o.0, o.1));
+        if mover == PieceKind::Pawn && o.1 != t.1 && self[t.0][t.1].is_empty() {
+            occupied &= !(1 << bitboard::square(o.0, t.1));
+        }
+
+        let mut side = self.turn().opposite();
+
+        loop {
+            //looking again each time brings in pieces behind the ones that have gone
+            let attackers = self.attackers_with(target, occupied) & occupied;
+
+            let (from, kind) = match self.least_valuable(attackers & self.pieces_of_colour(side)) {
+                Some(attacker) => attacker,
+                None => break,
+            };
+
+            //the king may only recapture if nothing can take it back
+            if kind == PieceKind::King && attackers & self.pieces_of_colour(side.opposite()) != 0 {
+                break
+            }
+
+            let last = gain[gain.len() - 1];
+            gain.push(on_square - last);
+            on_square = value(kind);
+
+            occupied &= !(1 << from);
+            side = side.opposite();
+        }
+
+        //each side only carries on trading if it does not lose by it
+        while gain.len() > 1 {
+            let last = gain.pop().unwrap();
+            let prev = gain.len() - 1;
+            gain[prev] = -(-gain[prev]).max(last);
+        }
+
+        gain[0]
+    }
+
+    /// Finds the least valuable of the given pieces, returning its square and kind.
+    fn least_valuable(&self, pieces: Bitboard) -> Option<(usize, PieceKind)> {
+        use PieceKind::*;
+
+        [Pawn, Knight, Bishop, Rook, Queen, King].iter()
+            .map(|&kind| (pieces & self.pieces_of_kind(kind), kind))
+            .find(|&(bb, _)| bb != 0)
+            .map(|(bb, kind)| (bb.trailing_zeros() as usize, kind))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn see(fen: &str, mov: &str) -> i32 {
+        let board = Board::from_fen(fen).unwrap();
+        let mov = board.parse_uci(mov).unwrap();
+        board.see(&mov)
+    }
+
+    #[test]
+    fn test_see_undefended() {
+        assert_eq!(see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"), 100);
+    }
+
+    #[test]
+    fn test_see_defended() {
+        //RxP is met by RxR
+        assert_eq!(see("1k1r4/1pp4p/p7/3rp3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"), -400);
+        //pawn takes a knight defended by a pawn
+        assert_eq!(see("4k3/8/2p5/3n4/4P3/8/8/4K3 w - - 0 1", "e4d5"), 200);
+    }
+
+    #[test]
+    fn test_see_xrays() {
+        //the queen behind the rook joins in once the rook has gone
+        assert_eq!(see("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5"), -200);
+        //doubled rooks win a pawn defended once
+        assert_eq!(see("4k3/4r3/8/4p3/8/8/4R3/4R1K1 w - - 0 1", "e2e5"), 100);
+    }
+
+    #[test]
+    fn test_see_quiet_moves() {
+        //moving to a square nothing attacks neither wins nor loses
+        assert_eq!(see("4k3/8/8/3p4/8/8/8/2N1K3 w - - 0 1", "c1e2"), 0);
+        assert_eq!(see("4k3/8/8/3p4/8/8/8/2N1K3 w - - 0 1", "c1b3"), 0);
+
+        //moving into an attacked square loses the piece
+        assert_eq!(see("4k3/8/8/3p4/8/8/3N4/4K3 w - - 0 1", "d2e4"), -300);
+        assert_eq!(see("4k3/8/2p5/8/8/8/8/3QK3 w - - 0 1", "d1d5"), -900);
+    }
+
+    #[test]
+    fn test_see_king_cannot_recapture_defended() {
+        assert_eq!(see("8/8/8/4k3/3p4/8/3R4/3RK3 w - - 0 1", "d2d4"), 100);
+        assert_eq!(see("8/8/8/4k3/3p4/8/3R4/4K3 w - - 0 1", "d2d4"), -400);
+    }
+
+    #[test]
+    fn test_see_en_passant_and_promotion() {
+        assert_eq!(see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), 100);
+        assert_eq!(see("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q"), 800);
+        assert_eq!(see("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8q"), 1300);
+    }
+}
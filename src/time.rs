@@ -0,0 +1,353 @@
+//! Deciding how long to think about a move.
+//!
+//! Each search gets two limits. The soft limit is checked between
+//! iterations: once it has passed, no new iteration is started, since it
+//! would most likely be cut short anyway. The hard limit is checked during
+//! the search and stops it outright. The soft limit is stretched when the
+//! search looks unsettled, as when the score drops or the best move keeps
+//! changing, but never past the hard limit.
+
+use std::time::{Duration, Instant};
+
+use crate::moves::Move;
+use crate::pieces::Colour;
+use crate::search::SearchLimits;
+
+/// Time kept back from every move for communication with the GUI,
+/// unless the limits ask for something else.
+pub const DEFAULT_MOVE_OVERHEAD: Duration = Duration::from_millis(20);
+
+/// How many moves the remaining time is spread over when the time control
+/// does not say how many are left, as in sudden death.
+const SUDDEN_DEATH_MOVES: u32 = 30;
+
+/// How much further than the soft limit the hard limit lies.
+const HARD_STRETCH: u32 = 4;
+
+/// How far a score may drop from one iteration to the next,
+/// in centipawns, before the search is given more time.
+const FAIL_LOW_MARGIN: i32 = 30;
+
+/// A source of the time passed since a search began.
+///
+/// The search uses the system clock; tests can substitute their own.
+pub trait Clock: Send {
+    /// The time passed since the search began.
+    fn elapsed(&self) -> Duration;
+}
+
+/// The system's monotonic clock, measured from when the value was created.
+#[derive(Clone, Copy, Debug)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    /// Starts measuring from now.
+    pub fn start() -> Self {
+        SystemClock {start: Instant::now()}
+    }
+}
+
+impl Clock for SystemClock {
+    fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Allocates time to a single search and tracks how it is spent.
+pub struct TimeManager {
+    clock: Box<dyn Clock>,
+    soft: Option<Duration>,
+    hard: Option<Duration>,
+    // how far the soft limit is stretched, in percent
+    scale: u32,
+    last_move: Option<Move>,
+    last_score: Option<i32>,
+}
+
+impl TimeManager {
+    /// Allocates time for the side to move under the given limits,
+    /// measured with the given clock.
+    ///
+    /// A fixed move time is used in full. With a clock, the remaining time,
+    /// less the move overhead, is split over the moves left until the next
+    /// time control, topped up with most of the increment. The hard limit
+    /// never comes near the time left on the clock, so the engine does not
+    /// lose on time even when every move runs into it.
+    pub fn new(limits: &SearchLimits, turn: Colour, clock: Box<dyn Clock>) -> Self {
+        let overhead = limits.move_overhead.unwrap_or(DEFAULT_MOVE_OVERHEAD);
+
+        let (soft, hard) = if limits.infinite {
+            (None, None)
+        } else if let Some(movetime) = limits.movetime {
+            let movetime = movetime.saturating_sub(overhead);
+            (Some(movetime), Some(movetime))
+        } else {
+            let clock = match turn {
+                Colour::White => limits.wtime.map(|t| (t, limits.winc.unwrap_or_default())),
+                Colour::Black => limits.btime.map(|t| (t, limits.binc.unwrap_or_default())),
+            };
+
+            match clock {
+                Some((time, inc)) => {
+                    let left = time.saturating_sub(overhead);
+                    let moves = limits.movestogo.unwrap_or(SUDDEN_DEATH_MOVES).max(1);
+
+                    let soft = (left / moves + inc * 3 / 4).min(left / 2);
+                    let hard = (soft * HARD_STRETCH).min(left * 3 / 4);
+                    (Some(soft), Some(hard))
+                }
+                None => (None, None),
+            }
+        };
+
+        TimeManager {
+            clock,
+            soft,
+            hard,
+            scale: 100,
+            last_move: None,
+            last_score: None,
+        }
+    }
+
+    /// The time passed since the search began.
+    pub fn elapsed(&self) -> Duration {
+        self.clock.elapsed()
+    }
+
+    /// The time after which no new iteration is started, stretched
+    /// by how unsettled the search is, or None if there is no limit.
+    pub fn soft_limit(&self) -> Option<Duration> {
+        self.soft.map(|soft| (soft * self.scale / 100).min(self.hard.unwrap_or(soft)))
+    }
+
+    /// The time at which the search is stopped outright, or None if there is no limit.
+    pub fn hard_limit(&self) -> Option<Duration> {
+        self.hard
+    }
+
+    /// Returns whether the hard limit has passed.
+    pub fn out_of_time(&self) -> bool {
+        self.hard.is_some_and(|hard| self.elapsed() >= hard)
+    }
+
+    /// Records the outcome of a completed iteration and returns
+    /// whether there is time to start another.
+    ///
+    /// A score that fell since the last iteration means the move found
+    /// before has run into trouble, and a best move that changed means
+    /// the search has not made up its mind, so either earns more time.
+    pub fn iteration_done(&mut self, best_move: Option<Move>, score: i32) -> bool {
+        let failed_low = self.last_score.is_some_and(|last| last - score > FAIL_LOW_MARGIN);
+        let unstable = self.last_move.is_some() && self.last_move != best_move;
+
+        self.scale = 100;
+        if failed_low {
+            self.scale += 50;
+        }
+        if unstable {
+            self.scale += 50;
+        }
+        self.last_move = best_move;
+        self.last_score = Some(score);
+
+        self.soft_limit().is_none_or(|soft| self.elapsed() < soft)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// A clock that only moves when told to.
+    #[derive(Clone, Default)]
+    struct MockClock(Arc<AtomicU64>);
+
+    impl MockClock {
+        fn advance(&self, ms: u64) {
+            self.0.fetch_add(ms, Ordering::SeqCst);
+        }
+    }
+
+    impl Clock for MockClock {
+        fn elapsed(&self) -> Duration {
+            Duration::from_millis(self.0.load(Ordering::SeqCst))
+        }
+    }
+
+    fn ms(ms: u64) -> Option<Duration> {
+        Some(Duration::from_millis(ms))
+    }
+
+    fn manager(limits: SearchLimits, turn: Colour) -> (TimeManager, MockClock) {
+        let clock = MockClock::default();
+        (TimeManager::new(&limits, turn, Box::new(clock.clone())), clock)
+    }
+
+    #[test]
+    fn test_movetime() {
+        let (time, clock) = manager(SearchLimits {
+            movetime: ms(1000),
+            move_overhead: ms(50),
+            ..SearchLimits::default()
+        }, Colour::White);
+
+        assert_eq!(time.soft_limit(), ms(950));
+        assert_eq!(time.hard_limit(), ms(950));
+
+        clock.advance(949);
+        assert!(!time.out_of_time());
+        clock.advance(1);
+        assert!(time.out_of_time());
+    }
+
+    #[test]
+    fn test_no_limits() {
+        let (mut time, clock) = manager(SearchLimits::default(), Colour::White);
+        clock.advance(1_000_000);
+
+        assert_eq!(time.hard_limit(), None);
+        assert!(!time.out_of_time());
+        assert!(time.iteration_done(None, 0));
+
+        //infinite overrides every other limit
+        let (time, _) = manager(SearchLimits {
+            infinite: true,
+            movetime: ms(10),
+            ..SearchLimits::default()
+        }, Colour::White);
+        assert_eq!(time.hard_limit(), None);
+    }
+
+    #[test]
+    fn test_clock_for_side_to_move() {
+        let limits = SearchLimits {
+            wtime: ms(60_000),
+            btime: ms(30_000),
+            winc: ms(1000),
+            binc: ms(0),
+            movestogo: Some(20),
+            move_overhead: ms(0),
+            ..SearchLimits::default()
+        };
+
+        let (white, _) = manager(limits.clone(), Colour::White);
+        assert_eq!(white.soft_limit(), ms(3000 + 750));
+        assert_eq!(white.hard_limit(), ms(15_000));
+
+        let (black, _) = manager(limits, Colour::Black);
+        assert_eq!(black.soft_limit(), ms(1500));
+        assert_eq!(black.hard_limit(), ms(6000));
+    }
+
+    #[test]
+    fn test_move_overhead() {
+        let limits = |overhead| SearchLimits {
+            btime: ms(10_000),
+            movestogo: Some(1),
+            move_overhead: overhead,
+            ..SearchLimits::default()
+        };
+
+        let (time, _) = manager(limits(ms(2000)), Colour::Black);
+        assert_eq!(time.hard_limit(), ms(6000));
+
+        let (time, _) = manager(limits(None), Colour::Black);
+        assert_eq!(time.hard_limit(), Some((ms(10_000).unwrap() - DEFAULT_MOVE_OVERHEAD) * 3 / 4));
+
+        //with less time than the overhead, move at once
+        let (time, _) = manager(limits(ms(20_000)), Colour::Black);
+        assert_eq!(time.hard_limit(), ms(0));
+    }
+
+    #[test]
+    fn test_never_flags_in_sudden_death() {
+        let mut left = Duration::from_secs(10);
+        let inc = Duration::from_millis(100);
+        let overhead = Duration::from_millis(20);
+
+        //play a long game where every move runs into the hard limit
+        for _ in 0..300 {
+            let (time, clock) = manager(SearchLimits {
+                wtime: Some(left),
+                winc: Some(inc),
+                move_overhead: Some(overhead),
+                ..SearchLimits::default()
+            }, Colour::White);
+
+            //the clock only counts whole milliseconds, so round up to reach the limit
+            let hard = time.hard_limit().unwrap();
+            clock.advance(hard.as_millis() as u64 + 1);
+            assert!(time.out_of_time());
+
+            let spent = time.elapsed() + overhead;
+            assert!(spent < left, "{:?} of {:?}", spent, left);
+            left = left - spent + inc;
+        }
+
+        assert!(left > inc);
+    }
+
+    #[test]
+    fn test_soft_limit_between_iterations() {
+        let (mut time, clock) = manager(SearchLimits {
+            wtime: ms(30_000),
+            move_overhead: ms(0),
+            ..SearchLimits::default()
+        }, Colour::White);
+        let mov = "e2e4".parse().ok();
+
+        assert_eq!(time.soft_limit(), ms(1000));
+        assert!(time.iteration_done(mov, 20));
+
+        clock.advance(999);
+        assert!(time.iteration_done(mov, 20));
+        clock.advance(1);
+        assert!(!time.iteration_done(mov, 20));
+        assert!(!time.out_of_time());
+    }
+
+    #[test]
+    fn test_extends_when_unsettled() {
+        let (mut time, clock) = manager(SearchLimits {
+            wtime: ms(30_000),
+            move_overhead: ms(0),
+            ..SearchLimits::default()
+        }, Colour::White);
+        let (e4, d4) = ("e2e4".parse().ok(), "d2d4".parse().ok());
+
+        time.iteration_done(e4, 50);
+        clock.advance(1200);
+
+        //the score falling away earns more time
+        assert!(time.iteration_done(e4, 0));
+        assert_eq!(time.soft_limit(), ms(1500));
+
+        //so does a change of mind, on top of that
+        assert!(time.iteration_done(d4, -50));
+        assert_eq!(time.soft_limit(), ms(2000));
+
+        //but a settled search goes back to the usual limit
+        assert!(!time.iteration_done(d4, -40));
+        assert_eq!(time.soft_limit(), ms(1000));
+    }
+
+    #[test]
+    fn test_extension_capped_by_hard_limit() {
+        let (mut time, _) = manager(SearchLimits {
+            wtime: ms(1000),
+            movestogo: Some(1),
+            move_overhead: ms(0),
+            ..SearchLimits::default()
+        }, Colour::White);
+
+        assert_eq!(time.soft_limit(), ms(500));
+        time.iteration_done("e2e4".parse().ok(), 100);
+        time.iteration_done("d2d4".parse().ok(), 0);
+        assert_eq!(time.soft_limit(), ms(750));
+    }
+}
@@ -0,0 +1,355 @@
+//! The transposition table, a cache of search results keyed by position hash.
+//! 
+//! Each bucket holds two entries: one kept for the deepest search of the
+//! positions that map to it, and one that always takes the latest result.
+//! Entries are stored as a pair of atomic words with the key XORed with
+//! the data, so the table can be shared between threads without locking;
+//! an entry torn by two threads writing at once simply fails to match.
+
+use std::fmt;
+use std::mem;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use crate::moves::{Move, MoveMarker};
+use crate::pieces::PieceKind;
+use crate::search::{MATE_SCORE, MAX_DEPTH};
+
+/// The table size used unless configured otherwise, in megabytes.
+pub const DEFAULT_SIZE_MB: usize = 16;
+
+/// Scores this close to the mate score are mates, stored relative to the node.
+const MATE_BOUND: i32 = MATE_SCORE - MAX_DEPTH as i32;
+
+/// How a stored score relates to the true score of the position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact.
+    Exact,
+    /// The search failed high; the true score is at least this.
+    Lower,
+    /// The search failed low; the true score is at most this.
+    Upper,
+}
+
+/// A search result read back from the table.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Entry {
+    /// The best move found, if the search got as far as finding one.
+    pub best_move: Option<Move>,
+    /// The score, adjusted to the ply it was probed at.
+    pub score: i32,
+    /// The depth the position was searched to.
+    pub depth: u32,
+    /// How the score bounds the true score.
+    pub bound: Bound,
+}
+
+#[derive(Default)]
+struct Slot {
+    // the position hash XORed with the data
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+#[derive(Default)]
+struct Bucket {
+    deepest: Slot,
+    latest: Slot,
+}
+
+/// A fixed-size hash table of search results that can be shared between threads.
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    // bumped every search, so entries from old searches can be told apart
+    age: AtomicU8,
+}
+
+impl TranspositionTable {
+    /// Creates an empty table taking up about `mb` megabytes.
+    pub fn new(mb: usize) -> Self {
+        let len = (mb.max(1) << 20) / mem::size_of::<Bucket>();
+
+        TranspositionTable {
+            buckets: (0..len).map(|_| Bucket::default()).collect(),
+            age: AtomicU8::new(0),
+        }
+    }
+
+    /// Returns the number of entries the table can hold.
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * 2
+    }
+
+    /// Forgets everything stored in the table.
+    pub fn clear(&self) {
+        for bucket in self.buckets.iter() {
+            for slot in [&bucket.deepest, &bucket.latest].iter() {
+                slot.key.store(0, Ordering::Relaxed);
+                slot.data.store(0, Ordering::Relaxed);
+            }
+        }
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    /// Marks the start of a new search, so entries from earlier searches
+    /// give way to new ones.
+    pub fn new_search(&self) {
+        self.age.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Looks up the position with the given hash, probed at `ply` from the root.
+    pub fn probe(&self, hash: u64, ply: usize) -> Option<Entry> {
+        let bucket = self.bucket(hash);
+
+        [&bucket.deepest, &bucket.latest].iter()
+            .find_map(|slot| slot.read(hash))
+            .map(|data| unpack(data, ply))
+    }
+
+    /// Stores a search result for the position with the given hash,
+    /// searched at `ply` from the root.
+    pub fn store(&self, hash: u64, ply: usize, entry: Entry) {
+        let bucket = self.bucket(hash);
+        let age = self.age.load(Ordering::Relaxed);
+        let data = pack(&entry, ply, age);
+
+        //the deep slot only gives way to deeper searches of the same age
+        let deepest = bucket.deepest.data.load(Ordering::Relaxed);
+        let replace = bucket.deepest.read(hash).is_some()
+            || entry.depth >= depth_of(deepest)
+            || age_of(deepest) != age;
+
+        if replace {
+            bucket.deepest.write(hash, data);
+        } else {
+            bucket.latest.write(hash, data);
+        }
+    }
+
+    /// Estimates how full the table is in permille, counting only
+    /// entries written during the current search.
+    pub fn hashfull(&self) -> u32 {
+        let age = self.age.load(Ordering::Relaxed);
+        let sample = &self.buckets[..self.buckets.len().min(500)];
+
+        let used = sample.iter()
+            .flat_map(|bucket| [&bucket.deepest, &bucket.latest])
+            .map(|slot| slot.data.load(Ordering::Relaxed))
+            .filter(|&data| data != 0 && age_of(data) == age)
+            .count();
+
+        (used * 1000 / (sample.len() * 2).max(1)) as u32
+    }
+
+    fn bucket(&self, hash: u64) -> &Bucket {
+        //scales the hash onto the table without the cost of a division
+        let index = ((hash as u128 * self.buckets.len() as u128) >> 64) as usize;
+        &self.buckets[index]
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(DEFAULT_SIZE_MB)
+    }
+}
+
+impl fmt::Debug for TranspositionTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TranspositionTable")
+            .field("capacity", &self.capacity())
+            .field("age", &self.age.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl Slot {
+    fn read(&self, hash: u64) -> Option<u64> {
+        let data = self.data.load(Ordering::Relaxed);
+        let key = self.key.load(Ordering::Relaxed);
+
+        if data != 0 && key ^ data == hash { Some(data) } else { None }
+    }
+
+    fn write(&self, hash: u64, data: u64) {
+        self.key.store(hash ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+}
+
+// Entries are packed into a word as follows, from the lowest bit:
+// move origin (6), move target (6), promotion (3), move present (1),
+// score (16), depth (8), bound (2), age (8). The bound is never zero,
+// so neither is a stored entry.
+
+fn pack(entry: &Entry, ply: usize, age: u8) -> u64 {
+    let mov = entry.best_move.map_or(0, |mov| {
+        let (o, t) = (mov.origin(), mov.target());
+        let promotion = match mov.promotion() {
+            None => 0,
+            Some(PieceKind::Knight) => 1,
+            Some(PieceKind::Bishop) => 2,
+            Some(PieceKind::Rook) => 3,
+            _ => 4,
+        };
+
+        (o.0 * 8 + o.1) as u64 | ((t.0 * 8 + t.1) as u64) << 6 | promotion << 12 | 1 << 15
+    });
+
+    //mates are stored as distance from this node, not from the root
+    let score = match entry.score {
+        s if s >= MATE_BOUND => s + ply as i32,
+        s if s <= -MATE_BOUND => s - ply as i32,
+        s => s,
+    };
+    let bound = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+
+    mov | (score as i16 as u16 as u64) << 16
+        | (entry.depth.min(255) as u64) << 32
+        | bound << 40
+        | (age as u64) << 42
+}
+
+fn unpack(data: u64, ply: usize) -> Entry {
+    let best_move = if data & 1 << 15 != 0 {
+        let square = |bits: u64| ((bits / 8) as usize, (bits % 8) as usize);
+        let origin = square(data & 63);
+        let target = square(data >> 6 & 63);
+
+        Some(match data >> 12 & 7 {
+            0 => Move::construct(target, origin),
+            1 => Move::promoting(target, origin, PieceKind::Knight),
+            2 => Move::promoting(target, origin, PieceKind::Bishop),
+            3 => Move::promoting(target, origin, PieceKind::Rook),
+            _ => Move::promoting(target, origin, PieceKind::Queen),
+        })
+    } else {
+        None
+    };
+
+    let score = match (data >> 16) as u16 as i16 as i32 {
+        s if s >= MATE_BOUND => s - ply as i32,
+        s if s <= -MATE_BOUND => s + ply as i32,
+        s => s,
+    };
+    let bound = match data >> 40 & 3 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+
+    Entry {
+        best_move,
+        score,
+        depth: depth_of(data),
+        bound,
+    }
+}
+
+fn depth_of(data: u64) -> u32 {
+    (data >> 32 & 255) as u32
+}
+
+fn age_of(data: u64) -> u8 {
+    (data >> 42 & 255) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(depth: u32, score: i32) -> Entry {
+        Entry {
+            best_move: Some("e2e4".parse().unwrap()),
+            score,
+            depth,
+            bound: Bound::Exact,
+        }
+    }
+
+    #[test]
+    fn test_tt_store_probe() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.probe(12345, 0), None);
+
+        let promotion = Entry {
+            best_move: Some("a7b8n".parse().unwrap()),
+            score: -250,
+            depth: 7,
+            bound: Bound::Upper,
+        };
+        tt.store(12345, 0, promotion);
+        assert_eq!(tt.probe(12345, 0), Some(promotion));
+
+        let quiet = Entry {best_move: None, bound: Bound::Lower, ..entry(0, 0)};
+        tt.store(999, 0, quiet);
+        assert_eq!(tt.probe(999, 0), Some(quiet));
+        assert_eq!(tt.probe(998, 0), None);
+    }
+
+    #[test]
+    fn test_tt_mate_scores_by_ply() {
+        let tt = TranspositionTable::new(1);
+
+        //mate in 3 plies from a node 4 plies deep, found later 2 plies deep
+        tt.store(1, 4, entry(5, MATE_SCORE - 7));
+        assert_eq!(tt.probe(1, 2).unwrap().score, MATE_SCORE - 5);
+
+        tt.store(2, 4, entry(5, -MATE_SCORE + 6));
+        assert_eq!(tt.probe(2, 0).unwrap().score, -MATE_SCORE + 2);
+
+        tt.store(3, 4, entry(5, 120));
+        assert_eq!(tt.probe(3, 0).unwrap().score, 120);
+    }
+
+    #[test]
+    fn test_tt_replacement() {
+        let tt = TranspositionTable::new(1);
+        //hashes this close together land in the same bucket
+        let (a, b, c) = (1, 2, 3);
+
+        tt.store(a, 0, entry(8, 1));
+        tt.store(b, 0, entry(3, 2));
+        assert_eq!(tt.probe(a, 0).unwrap().depth, 8);
+        assert_eq!(tt.probe(b, 0).unwrap().depth, 3);
+
+        //the shallow slot always takes the newest entry
+        tt.store(c, 0, entry(2, 3));
+        assert_eq!(tt.probe(a, 0).unwrap().depth, 8);
+        assert_eq!(tt.probe(b, 0), None);
+        assert_eq!(tt.probe(c, 0).unwrap().depth, 2);
+
+        //a deeper search takes over the deep slot
+        tt.store(b, 0, entry(9, 2));
+        assert_eq!(tt.probe(a, 0), None);
+        assert_eq!(tt.probe(b, 0).unwrap().depth, 9);
+
+        //and so does anything once the old entry is from an earlier search
+        tt.new_search();
+        tt.store(a, 0, entry(1, 1));
+        assert_eq!(tt.probe(a, 0).unwrap().depth, 1);
+        assert_eq!(tt.probe(b, 0), None);
+    }
+
+    #[test]
+    fn test_tt_hashfull() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.hashfull(), 0);
+
+        for i in 0..tt.capacity() as u64 * 2 {
+            tt.store(i.wrapping_mul(0x9e37_79b9_7f4a_7c15), 0, entry(i as u32 % 4, 0));
+        }
+        assert!(tt.hashfull() > 500, "{}", tt.hashfull());
+
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+
+        tt.store(7, 0, entry(1, 0));
+        tt.clear();
+        assert_eq!(tt.probe(7, 0), None);
+    }
+}
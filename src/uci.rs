@@ -0,0 +1,525 @@
+//! The Universal Chess Interface, for driving the engine from GUIs
+//! such as Arena or Cute Chess.
+
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::board::Board;
+use crate::search::{self, SearchLimits, SearchOptions, SearchResult, MATE_SCORE};
+use crate::time;
+use crate::tt::{self, TranspositionTable};
+
+/// The largest transposition table the `Hash` option allows, in megabytes.
+pub(crate) const MAX_HASH_MB: usize = 65536;
+
+/// The most threads the `Threads` option allows.
+pub(crate) const MAX_THREADS: usize = 256;
+
+/// The most lines the `MultiPV` option allows.
+const MAX_MULTIPV: usize = 256;
+
+/// The most time the `Move Overhead` option can keep back, in milliseconds.
+const MAX_MOVE_OVERHEAD_MS: u64 = 5000;
+
+/// The `check` options turning search techniques on and off, for testing their worth.
+const SEARCH_OPTIONS: [&str; 6] = [
+    "NullMove", "LMR", "Futility", "ReverseFutility", "Aspiration", "CheckExtensions",
+];
+
+/// Reads UCI commands from `input` line by line and writes responses
+/// to `output`, until `quit` is received or the input ends.
+pub fn run<R, W>(input: R, output: W)
+where R: BufRead, W: Write + Send + 'static {
+    let mut engine = Uci::new(output);
+
+    for line in input.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+
+        if !engine.handle(&line) {
+            break
+        }
+    }
+
+    engine.stop();
+}
+
+/// The state of a UCI session: the current position and any running search.
+struct Uci<W> {
+    board: Board,
+    output: Arc<Mutex<W>>,
+    stop: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+    tt: Arc<TranspositionTable>,
+    options: SearchOptions,
+    move_overhead: Duration,
+    threads: usize,
+    multipv: usize,
+}
+
+impl<W: Write + Send + 'static> Uci<W> {
+    fn new(output: W) -> Self {
+        Uci {
+            board: Board::init(),
+            output: Arc::new(Mutex::new(output)),
+            stop: Arc::new(AtomicBool::new(false)),
+            worker: None,
+            tt: Arc::new(TranspositionTable::default()),
+            options: SearchOptions::default(),
+            move_overhead: time::DEFAULT_MOVE_OVERHEAD,
+            threads: 1,
+            multipv: 1,
+        }
+    }
+
+    /// Handles a single command, returning false if the session should end.
+    fn handle(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        match tokens.first() {
+            Some(&"uci") => {
+                self.send(&format!("id name rustgambit {}", env!("CARGO_PKG_VERSION")));
+                self.send("id author cartoon-raccoon");
+                self.send(&format!(
+                    "option name Hash type spin default {} min 1 max {}",
+                    tt::DEFAULT_SIZE_MB, MAX_HASH_MB,
+                ));
+                self.send(&format!("option name Threads type spin default 1 min 1 max {}", MAX_THREADS));
+                self.send(&format!("option name MultiPV type spin default 1 min 1 max {}", MAX_MULTIPV));
+                self.send(&format!(
+                    "option name Move Overhead type spin default {} min 0 max {}",
+                    time::DEFAULT_MOVE_OVERHEAD.as_millis(), MAX_MOVE_OVERHEAD_MS,
+                ));
+                for name in SEARCH_OPTIONS.iter() {
+                    self.send(&format!("option name {} type check default true", name));
+                }
+                self.send("uciok");
+            }
+            Some(&"isready") => self.send("readyok"),
+            Some(&"ucinewgame") => {
+                self.stop();
+                self.board = Board::init();
+                self.tt.clear();
+            }
+            Some(&"position") => self.position(&tokens[1..]),
+            Some(&"go") => self.go(&tokens[1..]),
+            Some(&"stop") => self.stop(),
+            Some(&"setoption") => self.setoption(&tokens[1..]),
+            Some(&"d") => {
+                let display = format!("{}\nFen: {}", self.board, self.board.to_fen());
+                self.send(&display);
+            }
+            Some(&"quit") => return false,
+            Some(other) => self.send(&format!("info string unknown command '{}'", other)),
+            None => {}
+        }
+
+        true
+    }
+
+    fn send(&self, line: &str) {
+        send(&self.output, line)
+    }
+
+    /// Handles `position [startpos | fen <fen>] [moves <move>...]`.
+    /// 
+    /// The current position is left alone if any part fails to parse.
+    fn position(&mut self, args: &[&str]) {
+        let moves_at = args.iter().position(|&a| a == "moves").unwrap_or(args.len());
+
+        let board = match args.first() {
+            Some(&"startpos") => Ok(Board::init()),
+            Some(&"fen") => Board::from_fen(&args[1..moves_at].join(" "))
+                .map_err(|e| e.to_string()),
+            _ => Err("expected startpos or fen".to_string()),
+        };
+
+        let mut board = match board {
+            Ok(board) => board,
+            Err(e) => return self.send(&format!("info string invalid position: {}", e)),
+        };
+
+        for mov in args.iter().skip(moves_at + 1) {
+            match board.parse_uci(mov) {
+                Ok(mov) => board.play(mov),
+                Err(e) => return self.send(&format!("info string invalid position: {}", e)),
+            }
+        }
+
+        self.board = board;
+    }
+
+    /// Handles `go` by starting a search in the background,
+    /// which prints `bestmove` once it finishes.
+    fn go(&mut self, args: &[&str]) {
+        self.stop();
+
+        let mut limits = parse_limits(args);
+        self.stop.store(false, Ordering::SeqCst);
+        limits.stop = self.stop.clone();
+        limits.tt = Some(self.tt.clone());
+        limits.options = self.options;
+        limits.move_overhead = Some(self.move_overhead);
+        limits.threads = self.threads;
+        limits.multipv = self.multipv;
+
+        let board = self.board.clone();
+        let output = self.output.clone();
+        let tt = self.tt.clone();
+
+        self.worker = Some(thread::spawn(move || {
+            let start = Instant::now();
+            let result = search::search_with(&board, &limits, |result| {
+                for line in info(result, start.elapsed(), tt.hashfull()) {
+                    send(&output, &line);
+                }
+            });
+
+            //in infinite mode the GUI expects no bestmove until it sends stop
+            while limits.infinite && !limits.stop.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(1));
+            }
+
+            match result.best_move {
+                Some(mov) => send(&output, &format!("bestmove {}", mov)),
+                None => send(&output, "bestmove 0000"),
+            }
+        }));
+    }
+
+    /// Handles `setoption name <name> [value <value>]`.
+    fn setoption(&mut self, args: &[&str]) {
+        let value_at = args.iter().position(|&a| a == "value").unwrap_or(args.len());
+        let name = args.get(1..value_at).unwrap_or(&[]).join(" ");
+        let value = args.get(value_at + 1..).unwrap_or(&[]).join(" ");
+
+        match name.to_lowercase().as_str() {
+            "hash" => match value.parse::<usize>() {
+                Ok(mb) if (1..=MAX_HASH_MB).contains(&mb) => {
+                    self.stop();
+                    self.tt = Arc::new(TranspositionTable::new(mb));
+                }
+                _ => self.send(&format!("info string invalid value '{}' for Hash", value)),
+            },
+            "threads" => match value.parse::<usize>() {
+                Ok(threads) if (1..=MAX_THREADS).contains(&threads) => self.threads = threads,
+                _ => self.send(&format!("info string invalid value '{}' for Threads", value)),
+            },
+            "multipv" => match value.parse::<usize>() {
+                Ok(lines) if (1..=MAX_MULTIPV).contains(&lines) => self.multipv = lines,
+                _ => self.send(&format!("info string invalid value '{}' for MultiPV", value)),
+            },
+            "move overhead" => match value.parse::<u64>() {
+                Ok(ms) if ms <= MAX_MOVE_OVERHEAD_MS => self.move_overhead = Duration::from_millis(ms),
+                _ => self.send(&format!("info string invalid value '{}' for Move Overhead", value)),
+            },
+            lower => match (search_option(&mut self.options, lower), value.as_str()) {
+                (Some(flag), "true") => *flag = true,
+                (Some(flag), "false") => *flag = false,
+                (Some(_), _) => self.send(&format!("info string invalid value '{}' for {}", value, name)),
+                (None, _) => self.send(&format!("info string unknown option '{}'", name)),
+            },
+        }
+    }
+
+    /// Stops any running search and waits for it to print its move.
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+
+        if let Some(worker) = self.worker.take() {
+            worker.join().expect("search thread panicked");
+        }
+    }
+}
+
+pub(crate) fn send<W: Write>(output: &Mutex<W>, line: &str) {
+    let mut output = output.lock().unwrap();
+
+    //there is nowhere to report a broken output, so give up on the line quietly
+    let _ = writeln!(output, "{}", line);
+    let _ = output.flush();
+}
+
+/// Parses the arguments to `go`, ignoring any it does not recognise.
+fn parse_limits(args: &[&str]) -> SearchLimits {
+    let mut limits = SearchLimits::default();
+    let mut tokens = args.iter();
+
+    let millis = |v: Option<&&str>| v
+        .and_then(|v| v.parse::<i64>().ok())
+        //clocks can go negative in some GUIs when a player is about to flag
+        .map(|ms| Duration::from_millis(ms.max(0) as u64));
+
+    while let Some(&token) = tokens.next() {
+        match token {
+            "depth" => limits.depth = tokens.next().and_then(|v| v.parse().ok()),
+            "movetime" => limits.movetime = millis(tokens.next()),
+            "wtime" => limits.wtime = millis(tokens.next()),
+            "btime" => limits.btime = millis(tokens.next()),
+            "winc" => limits.winc = millis(tokens.next()),
+            "binc" => limits.binc = millis(tokens.next()),
+            "movestogo" => limits.movestogo = tokens.next().and_then(|v| v.parse().ok()),
+            "infinite" => limits.infinite = true,
+            _ => {}
+        }
+    }
+
+    limits
+}
+
+/// Returns the switch behind one of the `SEARCH_OPTIONS`, given its name in lowercase.
+fn search_option<'a>(options: &'a mut SearchOptions, name: &str) -> Option<&'a mut bool> {
+    match name {
+        "nullmove" => Some(&mut options.null_move),
+        "lmr" => Some(&mut options.late_move_reductions),
+        "futility" => Some(&mut options.futility),
+        "reversefutility" => Some(&mut options.reverse_futility),
+        "aspiration" => Some(&mut options.aspiration),
+        "checkextensions" => Some(&mut options.check_extensions),
+        _ => None,
+    }
+}
+
+/// Formats a completed iteration as `info` lines, one per line of play,
+/// numbered with `multipv` when there is more than one.
+fn info(result: &SearchResult, elapsed: Duration, hashfull: u32) -> Vec<String> {
+    let millis = elapsed.as_millis() as u64;
+    let numbered = result.lines.len() > 1;
+
+    result.lines.iter().enumerate().map(|(k, line)| {
+        let pv: Vec<String> = line.pv.iter().map(|m| m.to_string()).collect();
+
+        format!(
+            "info {}depth {} score {} nodes {} nps {} hashfull {} time {} pv {}",
+            if numbered { format!("multipv {} ", k + 1) } else { String::new() },
+            result.depth,
+            score(line.score),
+            result.nodes,
+            result.nodes * 1000 / millis.max(1),
+            hashfull,
+            millis,
+            pv.join(" "),
+        )
+    }).collect()
+}
+
+/// Formats a score as `cp <centipawns>`, or `mate <moves>` for forced mates,
+/// negative if the side to move is getting mated.
+fn score(score: i32) -> String {
+    //anything this close to the mate score is a mate found within the search
+    const MATE_BOUND: i32 = MATE_SCORE - 1000;
+
+    if score >= MATE_BOUND {
+        format!("mate {}", (MATE_SCORE - score + 1) / 2)
+    } else if score <= -MATE_BOUND {
+        format!("mate -{}", (MATE_SCORE + score) / 2)
+    } else {
+        format!("cp {}", score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// An output that can still be read after the session takes ownership of it.
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn session(script: &str) -> Vec<String> {
+        let buf = SharedBuf::default();
+        run(Cursor::new(script.to_string()), buf.clone());
+
+        let output = buf.0.lock().unwrap();
+        String::from_utf8(output.clone()).unwrap()
+            .lines()
+            .map(|l| l.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_uci_handshake() {
+        let output = session("uci\nisready\nquit\n");
+
+        assert!(output[0].starts_with("id name rustgambit"));
+        assert_eq!(output[1], "id author cartoon-raccoon");
+        assert!(output.contains(&"uciok".to_string()));
+        assert_eq!(output.last().unwrap(), "readyok");
+    }
+
+    #[test]
+    fn test_uci_position_moves() {
+        let output = session("position startpos moves e2e4 e7e5 g1f3\nd\n");
+
+        assert_eq!(
+            output.last().unwrap(),
+            "Fen: rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+    }
+
+    #[test]
+    fn test_uci_position_fen() {
+        let fen = "4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1";
+        let output = session(&format!("position fen {} moves e1e2\nd\n", fen));
+
+        assert_eq!(output.last().unwrap(), "Fen: 4k3/8/8/3q4/8/8/4K3/3R4 b - - 1 1");
+    }
+
+    #[test]
+    fn test_uci_invalid_position() {
+        let output = session("position startpos moves e2e5\nd\n");
+
+        assert!(output[0].starts_with("info string invalid position"));
+        assert_eq!(*output.last().unwrap(), format!("Fen: {}", crate::fen::STARTING_FEN));
+    }
+
+    #[test]
+    fn test_uci_go() {
+        let output = session("position fen 4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1\ngo depth 1\nquit\n");
+
+        assert!(output[0].starts_with("info depth"));
+        assert_eq!(output.last().unwrap(), "bestmove d1d5");
+    }
+
+    #[test]
+    fn test_uci_go_infinite_waits_for_stop() {
+        let output = session("position startpos\ngo infinite\nisready\nstop\n");
+
+        assert_eq!(output[0], "readyok");
+        assert!(output.last().unwrap().starts_with("bestmove"));
+    }
+
+    #[test]
+    fn test_uci_no_moves() {
+        let output = session("position fen 7k/5Q2/6K1/8/8/8/8/8 b - - 0 1\ngo wtime 1000 btime 1000\n");
+
+        assert_eq!(output.last().unwrap(), "bestmove 0000");
+    }
+
+    #[test]
+    fn test_uci_hash_option() {
+        let output = session("uci\nsetoption name Hash value 1\nsetoption name Hash value 0\nquit\n");
+
+        assert!(output.contains(&"option name Hash type spin default 16 min 1 max 65536".to_string()));
+        assert_eq!(output.last().unwrap(), "info string invalid value '0' for Hash");
+    }
+
+    #[test]
+    fn test_uci_search_options() {
+        let mut engine = Uci::new(Vec::new());
+        engine.handle("setoption name NullMove value false");
+        engine.handle("setoption name lmr value false");
+        engine.handle("setoption name Futility value maybe");
+
+        let expected = SearchOptions {
+            null_move: false,
+            late_move_reductions: false,
+            ..SearchOptions::default()
+        };
+        assert_eq!(engine.options, expected);
+
+        let output = session("uci\nsetoption name Aspiration value false\nsetoption name Futility value maybe\nquit\n");
+        assert!(output.contains(&"option name CheckExtensions type check default true".to_string()));
+        assert_eq!(output.last().unwrap(), "info string invalid value 'maybe' for Futility");
+    }
+
+    #[test]
+    fn test_uci_threads_option() {
+        let mut engine = Uci::new(Vec::new());
+        engine.handle("setoption name Threads value 4");
+        assert_eq!(engine.threads, 4);
+        engine.handle("setoption name Threads value 0");
+        assert_eq!(engine.threads, 4);
+
+        let output = session("uci\nsetoption name Threads value 2\nposition startpos\ngo depth 4\n");
+        assert!(output.contains(&"option name Threads type spin default 1 min 1 max 256".to_string()));
+        assert!(output.last().unwrap().starts_with("bestmove"));
+    }
+
+    #[test]
+    fn test_uci_multipv() {
+        let output = session("uci\nquit\n");
+        assert!(output.contains(&"option name MultiPV type spin default 1 min 1 max 256".to_string()));
+
+        //let the search run to its depth rather than stopping it at the end of input
+        let buf = SharedBuf::default();
+        let mut engine = Uci::new(buf.clone());
+        engine.handle("setoption name MultiPV value 3");
+        engine.handle("go depth 3");
+        engine.worker.take().unwrap().join().unwrap();
+
+        let output: Vec<String> = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap()
+            .lines()
+            .map(|l| l.to_string())
+            .collect();
+        for depth in 1..=3 {
+            for k in 1..=3 {
+                let prefix = format!("info multipv {} depth {} ", k, depth);
+                assert!(output.iter().any(|l| l.starts_with(&prefix)), "{}", prefix);
+            }
+        }
+        assert!(output.last().unwrap().starts_with("bestmove"));
+
+        //a position with fewer moves than lines asked for gives what it has
+        let output = session("setoption name MultiPV value 5\nposition fen 7k/8/8/8/8/8/8/K7 w - - 0 1\ngo depth 1\n");
+        assert_eq!(output.len(), 4);
+        assert!(output[2].starts_with("info multipv 3 depth 1 "));
+
+        let output = session("setoption name MultiPV value 0\n");
+        assert_eq!(output, vec!["info string invalid value '0' for MultiPV"]);
+    }
+
+    #[test]
+    fn test_uci_move_overhead() {
+        let mut engine = Uci::new(Vec::new());
+        assert_eq!(engine.move_overhead, time::DEFAULT_MOVE_OVERHEAD);
+
+        engine.handle("setoption name Move Overhead value 150");
+        assert_eq!(engine.move_overhead, Duration::from_millis(150));
+        engine.handle("setoption name Move Overhead value -1");
+        assert_eq!(engine.move_overhead, Duration::from_millis(150));
+
+        let output = session("uci\nsetoption name Move Overhead value 9999\nquit\n");
+        assert!(output.contains(&"option name Move Overhead type spin default 20 min 0 max 5000".to_string()));
+        assert_eq!(output.last().unwrap(), "info string invalid value '9999' for Move Overhead");
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        let limits = parse_limits(&[
+            "wtime", "60000", "btime", "-5", "winc", "1000", "binc", "1000",
+            "movestogo", "20", "depth", "8", "bogus",
+        ]);
+
+        assert_eq!(limits.wtime, Some(Duration::from_secs(60)));
+        assert_eq!(limits.btime, Some(Duration::from_secs(0)));
+        assert_eq!(limits.binc, Some(Duration::from_secs(1)));
+        assert_eq!(limits.movestogo, Some(20));
+        assert_eq!(limits.depth, Some(8));
+        assert!(!limits.infinite);
+    }
+
+    #[test]
+    fn test_score_format() {
+        assert_eq!(score(35), "cp 35");
+        assert_eq!(score(MATE_SCORE - 1), "mate 1");
+        assert_eq!(score(MATE_SCORE - 3), "mate 2");
+        assert_eq!(score(-MATE_SCORE + 2), "mate -1");
+    }
+}
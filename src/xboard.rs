@@ -0,0 +1,558 @@
+//! The XBoard protocol (Chess Engine Communication Protocol, version 2),
+//! for tournament tooling that does not speak UCI.
+
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::board::{Board, GameState};
+use crate::pieces::Colour;
+use crate::search::{self, SearchLimits};
+use crate::tt::TranspositionTable;
+use crate::uci::{send, MAX_HASH_MB, MAX_THREADS};
+
+/// Time given to each move when the interface has set no time control at all.
+const DEFAULT_MOVE_TIME: Duration = Duration::from_secs(5);
+
+/// Reads XBoard commands from `input` line by line and writes responses
+/// to `output`, until `quit` is received or the input ends.
+pub fn run<R, W>(input: R, output: W)
+where R: BufRead, W: Write + Send + 'static {
+    let mut engine = XBoard::new(output);
+
+    for line in input.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+
+        if !engine.handle(&line) {
+            engine.abort();
+            return
+        }
+    }
+
+    //with nobody left to send `?`, a script piped in still gets its reply
+    engine.move_now();
+}
+
+/// The time control set with `level`, `st` and `sd`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct TimeControl {
+    /// Moves per session, or 0 for the whole game.
+    moves_per_session: u32,
+    /// Time on each clock at the start of a session, if set by `level`.
+    base: Option<Duration>,
+    /// Increment per move.
+    increment: Duration,
+    /// Fixed time per move, overriding the clock.
+    per_move: Option<Duration>,
+    /// Maximum search depth.
+    depth: Option<u32>,
+}
+
+/// The state of an XBoard session.
+struct XBoard<W> {
+    board: Arc<Mutex<Board>>,
+    output: Arc<Mutex<W>>,
+    stop: Arc<AtomicBool>,
+    // raised with `stop` when the search is abandoned rather than cut short,
+    // so its move is thrown away instead of played
+    abort: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+    // while forced, the engine only records moves and never replies
+    force: bool,
+    engine: Colour,
+    control: TimeControl,
+    own_time: Option<Duration>,
+    opponent_time: Option<Duration>,
+    tt: Arc<TranspositionTable>,
+    cores: usize,
+}
+
+impl<W: Write + Send + 'static> XBoard<W> {
+    fn new(output: W) -> Self {
+        XBoard {
+            board: Arc::new(Mutex::new(Board::init())),
+            output: Arc::new(Mutex::new(output)),
+            stop: Arc::new(AtomicBool::new(false)),
+            abort: Arc::new(AtomicBool::new(false)),
+            worker: None,
+            force: false,
+            engine: Colour::Black,
+            control: TimeControl::default(),
+            own_time: None,
+            opponent_time: None,
+            tt: Arc::new(TranspositionTable::default()),
+            cores: 1,
+        }
+    }
+
+    /// Handles a single command, returning false if the session should end.
+    fn handle(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let arg = |i: usize| tokens.get(i).copied().unwrap_or("");
+
+        match tokens.first() {
+            Some(&"xboard") | Some(&"accepted") | Some(&"rejected") => {}
+            Some(&"protover") => self.send(&format!(
+                "feature myname=\"rustgambit {}\" ping=1 setboard=1 usermove=1 \
+                 memory=1 smp=1 san=0 time=1 draw=0 sigint=0 sigterm=0 colors=0 analyze=0 done=1",
+                env!("CARGO_PKG_VERSION"),
+            )),
+            Some(&"new") => {
+                self.abort();
+                *self.board.lock().unwrap() = Board::init();
+                self.force = false;
+                self.engine = Colour::Black;
+                self.control.per_move = None;
+                self.control.depth = None;
+                self.tt.clear();
+            }
+            Some(&"force") => {
+                self.abort();
+                self.force = true;
+            }
+            Some(&"go") => {
+                self.abort();
+                self.force = false;
+                self.engine = self.board.lock().unwrap().turn();
+                self.think();
+            }
+            Some(&"usermove") => self.usermove(arg(1)),
+            Some(&"?") => self.move_now(),
+            Some(&"ping") => self.send(&format!("pong {}", arg(1))),
+            Some(&"undo") => self.take_back(1),
+            Some(&"remove") => self.take_back(2),
+            Some(&"setboard") => self.setboard(&tokens[1..].join(" ")),
+            Some(&"level") => self.level(arg(1), arg(2), arg(3)),
+            Some(&"st") => {
+                self.control.per_move = arg(1).parse().ok().map(Duration::from_secs);
+            }
+            Some(&"sd") => self.control.depth = arg(1).parse().ok(),
+            Some(&"memory") => match arg(1).parse::<usize>() {
+                Ok(mb) => {
+                    self.abort();
+                    self.tt = Arc::new(TranspositionTable::new(mb.clamp(1, MAX_HASH_MB)));
+                }
+                Err(_) => self.send(&format!("Error (invalid memory size): {}", arg(1))),
+            },
+            Some(&"cores") => match arg(1).parse::<usize>() {
+                Ok(cores) if cores > 0 => self.cores = cores.min(MAX_THREADS),
+                _ => self.send(&format!("Error (invalid core count): {}", arg(1))),
+            },
+            Some(&"time") => self.own_time = centiseconds(arg(1)),
+            Some(&"otim") => self.opponent_time = centiseconds(arg(1)),
+            Some(&"result") => {
+                self.abort();
+                self.force = true;
+            }
+            Some(&"quit") => return false,
+            //moves may still arrive bare if the interface ignored usermove=1
+            Some(token) if token.parse::<crate::moves::Move>().is_ok() => self.usermove(token),
+            Some(&"random") | Some(&"post") | Some(&"nopost") | Some(&"hard") | Some(&"easy")
+            | Some(&"computer") | Some(&"white") | Some(&"black") | Some(&"name")
+            | Some(&"rating") | Some(&"ics") => {}
+            Some(other) => self.send(&format!("Error (unknown command): {}", other)),
+            None => {}
+        }
+
+        true
+    }
+
+    fn send(&self, line: &str) {
+        send(&self.output, line)
+    }
+
+    /// Plays the opponent's move and replies if it is the engine's turn.
+    fn usermove(&mut self, text: &str) {
+        self.abort();
+
+        {
+            let mut board = self.board.lock().unwrap();
+            match board.parse_uci(text) {
+                Ok(mov) => board.play(mov),
+                Err(_) => {
+                    drop(board);
+                    return self.send(&format!("Illegal move: {}", text))
+                }
+            }
+
+            if let Some(result) = result(&mut board) {
+                drop(board);
+                return self.send(&result)
+            }
+        }
+
+        let turn = self.board.lock().unwrap().turn();
+        if !self.force && turn == self.engine {
+            self.think();
+        }
+    }
+
+    fn take_back(&mut self, plies: usize) {
+        self.abort();
+
+        let mut board = self.board.lock().unwrap();
+        for _ in 0..plies {
+            board.unmake_move();
+        }
+    }
+
+    fn setboard(&mut self, fen: &str) {
+        self.abort();
+
+        match Board::from_fen(fen) {
+            Ok(board) => *self.board.lock().unwrap() = board,
+            Err(e) => self.send(&format!("tellusererror Illegal position: {}", e)),
+        }
+    }
+
+    /// Handles `level MPS BASE INC`, where BASE is minutes or `minutes:seconds`.
+    /// 
+    /// Both clocks start from BASE until `time` and `otim` say otherwise.
+    fn level(&mut self, mps: &str, base: &str, inc: &str) {
+        self.control.moves_per_session = mps.parse().unwrap_or(0);
+        self.control.base = minutes(base);
+        self.own_time = None;
+        self.opponent_time = None;
+        self.control.increment = inc.parse::<f64>()
+            .ok()
+            .filter(|inc| *inc >= 0.0)
+            .map(Duration::from_secs_f64)
+            .unwrap_or_default();
+        self.control.per_move = None;
+    }
+
+    /// Translates the time control into search limits for the side to move.
+    fn limits(&self, board: &Board) -> SearchLimits {
+        let mut limits = SearchLimits {
+            depth: self.control.depth,
+            movetime: self.control.per_move,
+            ..SearchLimits::default()
+        };
+
+        let own_time = self.own_time.or(self.control.base);
+        let opponent_time = self.opponent_time.or(self.control.base);
+
+        //with nothing to go on, think for a fixed while rather than forever
+        if limits.movetime.is_none() && limits.depth.is_none() && own_time.is_none() {
+            limits.movetime = Some(DEFAULT_MOVE_TIME);
+        }
+
+        if limits.movetime.is_none() {
+            let own_inc = Some(self.control.increment);
+            let opponent_inc = Some(self.control.increment);
+
+            match board.turn() {
+                Colour::White => {
+                    limits.wtime = own_time;
+                    limits.winc = own_inc;
+                    limits.btime = opponent_time;
+                    limits.binc = opponent_inc;
+                }
+                Colour::Black => {
+                    limits.btime = own_time;
+                    limits.binc = own_inc;
+                    limits.wtime = opponent_time;
+                    limits.winc = opponent_inc;
+                }
+            }
+
+            let mps = self.control.moves_per_session;
+            if mps > 0 {
+                //count the moves this side has made since the last time control
+                let made = (board.fullmove_number() - 1) % mps;
+                limits.movestogo = Some(mps - made);
+            }
+        }
+
+        limits
+    }
+
+    /// Starts searching in the background for the engine's move,
+    /// which is played and announced once the search finishes,
+    /// unless the search is abandoned first.
+    fn think(&mut self) {
+        let board = self.board.lock().unwrap().clone();
+
+        let mut limits = self.limits(&board);
+        self.stop.store(false, Ordering::SeqCst);
+        self.abort.store(false, Ordering::SeqCst);
+        limits.stop = self.stop.clone();
+        limits.tt = Some(self.tt.clone());
+        limits.threads = self.cores;
+
+        let shared = self.board.clone();
+        let output = self.output.clone();
+        let abort = self.abort.clone();
+
+        self.worker = Some(thread::spawn(move || {
+            let result = search::search(&board, &limits);
+            if abort.load(Ordering::SeqCst) {
+                return
+            }
+
+            let mut board = shared.lock().unwrap();
+            let mov = match result.best_move {
+                Some(mov) => mov,
+                //there is no move to make, but the game still needs its result
+                None => {
+                    if let Some(result) = self::result(&mut board) {
+                        send(&output, &result);
+                    }
+                    return
+                }
+            };
+
+            board.play(mov);
+            send(&output, &format!("move {}", mov));
+
+            if let Some(result) = self::result(&mut board) {
+                send(&output, &result);
+            }
+        }));
+    }
+
+    /// Stops any running search and waits for it to play its move.
+    fn move_now(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+
+        if let Some(worker) = self.worker.take() {
+            worker.join().expect("search thread panicked");
+        }
+    }
+
+    /// Abandons any running search, leaving the board as it is and playing nothing.
+    fn abort(&mut self) {
+        self.abort.store(true, Ordering::SeqCst);
+        self.move_now();
+    }
+}
+
+/// Returns the result line to announce if the game is over.
+fn result(board: &mut Board) -> Option<String> {
+    let result = match board.eval_gamestate() {
+        GameState::Checkmate(Colour::White) => "0-1 {Black mates}",
+        GameState::Checkmate(Colour::Black) => "1-0 {White mates}",
+        GameState::Stalemate => "1/2-1/2 {Stalemate}",
+        GameState::FiftyMoveRule => "1/2-1/2 {50 move rule}",
+        GameState::ThreefoldRepetition => "1/2-1/2 {3-fold repetition}",
+        GameState::InsufficientMaterial => "1/2-1/2 {Insufficient material}",
+        GameState::None | GameState::Check(_) => return None,
+    };
+
+    Some(result.to_string())
+}
+
+/// Parses a BASE from `level`, either whole minutes or `minutes:seconds`.
+fn minutes(value: &str) -> Option<Duration> {
+    let (minutes, seconds) = match value.find(':') {
+        Some(colon) => (&value[..colon], value[colon + 1..].parse().ok()?),
+        None => (value, 0),
+    };
+
+    let minutes: u64 = minutes.parse().ok()?;
+    Some(Duration::from_secs(minutes * 60 + seconds))
+}
+
+fn centiseconds(value: &str) -> Option<Duration> {
+    value.parse::<i64>()
+        .ok()
+        .map(|cs| Duration::from_millis(cs.max(0) as u64 * 10))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// An output that can still be read after the session takes ownership of it.
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn session(script: &str) -> Vec<String> {
+        let buf = SharedBuf::default();
+        run(Cursor::new(script.to_string()), buf.clone());
+
+        let output = buf.0.lock().unwrap();
+        String::from_utf8(output.clone()).unwrap()
+            .lines()
+            .map(|l| l.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_xboard_handshake() {
+        let output = session("xboard\nprotover 2\nping 7\nquit\n");
+
+        assert!(output[0].starts_with("feature myname=\"rustgambit"));
+        assert!(output[0].contains("usermove=1") && output[0].ends_with("done=1"));
+        assert_eq!(output[1], "pong 7");
+    }
+
+    #[test]
+    fn test_xboard_replies_to_usermove() {
+        let output = session("xboard\nnew\nusermove e2e4\n");
+
+        assert_eq!(output.len(), 1);
+        assert!(output[0].starts_with("move "));
+    }
+
+    #[test]
+    fn test_xboard_force_and_go() {
+        let output = session(
+            "new\nforce\nsetboard 4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1\nping 1\ngo\n"
+        );
+
+        assert_eq!(output, vec!["pong 1", "move d1d5"]);
+    }
+
+    #[test]
+    fn test_xboard_abandons_search() {
+        //only `?` or the clock may make the engine move, not a command that moves on
+        for command in ["force", "new", "undo", "setboard 4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1"].iter() {
+            let output = session(&format!("new\nusermove e2e4\n{}\nping 1\nquit\n", command));
+            assert_eq!(output, vec!["pong 1"], "{}", command);
+        }
+
+        let output = session("new\nforce\ngo\n?\nping 1\nquit\n");
+        assert_eq!(output.len(), 2);
+        assert!(output[0].starts_with("move "));
+    }
+
+    #[test]
+    fn test_xboard_go_without_moves() {
+        let output = session("new\nforce\nsetboard R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1\ngo\n");
+        assert_eq!(output, vec!["1-0 {White mates}"]);
+
+        let output = session("new\nforce\nsetboard 7k/5Q2/6K1/8/8/8/8/8 b - - 0 1\ngo\n");
+        assert_eq!(output, vec!["1/2-1/2 {Stalemate}"]);
+    }
+
+    #[test]
+    fn test_xboard_illegal_move() {
+        let output = session("new\nforce\nusermove e2e5\ne2e4\nping 1\n");
+
+        assert_eq!(output, vec!["Illegal move: e2e5", "pong 1"]);
+    }
+
+    #[test]
+    fn test_xboard_undo_and_remove() {
+        let buf = SharedBuf::default();
+        let mut engine = XBoard::new(buf);
+
+        for line in ["new", "force", "usermove e2e4", "usermove e7e5", "usermove g1f3"].iter() {
+            engine.handle(line);
+        }
+        engine.handle("undo");
+        assert_eq!(engine.board.lock().unwrap().history().len(), 2);
+
+        engine.handle("remove");
+        assert_eq!(engine.board.lock().unwrap().to_fen(), crate::fen::STARTING_FEN);
+    }
+
+    #[test]
+    fn test_xboard_reports_result() {
+        let output = session(
+            "new\nforce\nsetboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\nusermove a1a8\n"
+        );
+        assert_eq!(output, vec!["1-0 {White mates}"]);
+
+        let output = session("new\nforce\nsetboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo\n");
+        assert_eq!(output, vec!["move a1a8", "1-0 {White mates}"]);
+    }
+
+    #[test]
+    fn test_xboard_time_controls() {
+        let buf = SharedBuf::default();
+        let mut engine = XBoard::new(buf);
+
+        engine.handle("level 40 5:30 2");
+
+        //until the interface sends the clocks, both start from the base
+        let limits = engine.limits(&Board::init());
+        assert_eq!(limits.wtime, Some(Duration::from_secs(330)));
+        assert_eq!(limits.btime, Some(Duration::from_secs(330)));
+
+        engine.handle("time 12000");
+        engine.handle("otim 9000");
+        engine.handle("sd 6");
+
+        let limits = engine.limits(&Board::init());
+        assert_eq!(limits.wtime, Some(Duration::from_secs(120)));
+        assert_eq!(limits.btime, Some(Duration::from_secs(90)));
+        assert_eq!(limits.winc, Some(Duration::from_secs(2)));
+        assert_eq!(limits.movestogo, Some(40));
+        assert_eq!(limits.depth, Some(6));
+        assert_eq!(limits.movetime, None);
+
+        engine.handle("st 10");
+        assert_eq!(engine.limits(&Board::init()).movetime, Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_xboard_default_move_time() {
+        let buf = SharedBuf::default();
+        let mut engine = XBoard::new(buf);
+
+        let limits = engine.limits(&Board::init());
+        assert_eq!(limits.movetime, Some(DEFAULT_MOVE_TIME));
+        assert_eq!(limits.wtime, None);
+
+        //a depth limit is enough on its own
+        engine.handle("sd 4");
+        assert_eq!(engine.limits(&Board::init()).movetime, None);
+    }
+
+    #[test]
+    fn test_level_base() {
+        assert_eq!(minutes("5"), Some(Duration::from_secs(300)));
+        assert_eq!(minutes("0:30"), Some(Duration::from_secs(30)));
+        assert_eq!(minutes("2:05"), Some(Duration::from_secs(125)));
+        assert_eq!(minutes("x"), None);
+        assert_eq!(minutes("1:x"), None);
+    }
+
+    #[test]
+    fn test_xboard_memory() {
+        let buf = SharedBuf::default();
+        let mut engine = XBoard::new(buf);
+
+        engine.handle("memory 1");
+        assert!(engine.tt.capacity() < TranspositionTable::default().capacity());
+
+        //sizes outside what the Hash option allows are held to its bounds
+        engine.handle("memory 0");
+        assert_eq!(engine.tt.capacity(), TranspositionTable::new(1).capacity());
+    }
+
+    #[test]
+    fn test_xboard_cores() {
+        let buf = SharedBuf::default();
+        let mut engine = XBoard::new(buf.clone());
+
+        engine.handle("cores 4");
+        assert_eq!(engine.cores, 4);
+        engine.handle("cores 0");
+        assert_eq!(engine.cores, 4);
+        let output = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(output.lines().last(), Some("Error (invalid core count): 0"));
+
+        //more cores than the Threads option allows are held to its bound
+        engine.handle("cores 100000");
+        assert_eq!(engine.cores, MAX_THREADS);
+    }
+}
@@ -0,0 +1,24 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[test]
+fn test_binary_plays_a_move() {
+    let mut engine = Command::new(env!("CARGO_BIN_EXE_rustgambit"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start engine");
+
+    engine.stdin.take().unwrap()
+        .write_all(b"uci\nisready\nposition startpos moves e2e4\ngo depth 1\nquit\n")
+        .unwrap();
+
+    let output = engine.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+
+    assert!(output.status.success());
+    assert!(lines.contains(&"uciok"));
+    assert!(lines.contains(&"readyok"));
+    assert!(lines.last().unwrap().starts_with("bestmove "));
+}